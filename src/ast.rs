@@ -1,62 +1,210 @@
-use crate::tokens::Token;
+use crate::environment::Environment;
+use crate::format::format_number;
+use crate::tokens::{Span, Token};
+use crate::value::{RuntimeError, Value};
 
 pub trait Expr {
+    /// Print in infix notation, e.g. `1 + 2`.
     fn print(&self) -> String;
-    fn eval(&self) -> Box<dyn Expr>;
+    /// Print in fully parenthesised prefix notation, e.g. `(+ 1 2)`.
+    fn print_lisp(&self) -> String;
+    fn eval(&self, env: &mut Environment) -> Result<Value, RuntimeError>;
+
+    /// How tightly this node binds, used to decide where infix printing needs parentheses.
+    fn precedence(&self) -> u8 {
+        PRIMARY_PRECEDENCE
+    }
+
+    /// The source this node was parsed from, for nodes that track it.
+    fn span(&self) -> Option<Span> {
+        None
+    }
+}
+
+const ASSIGNMENT_PRECEDENCE: u8 = 0;
+const UNARY_PRECEDENCE: u8 = 7;
+const PRIMARY_PRECEDENCE: u8 = 8;
+
+/// Precedence of a binary operator, higher binds tighter.
+fn operator_precedence(op: &Token) -> u8 {
+    match op {
+        Token::Or => 1,
+        Token::And => 2,
+        Token::EqualEqual | Token::BangEqual => 3,
+        Token::Greater | Token::GreaterEqual | Token::Less | Token::LessEqual => 4,
+        Token::Plus | Token::Minus => 5,
+        Token::Star | Token::Slash => 6,
+        _ => unreachable!(),
+    }
+}
+
+fn operator_str(op: &Token) -> &'static str {
+    match op {
+        Token::Plus => "+",
+        Token::Minus => "-",
+        Token::Star => "*",
+        Token::Slash => "/",
+        Token::Bang => "!",
+        Token::BangEqual => "!=",
+        Token::EqualEqual => "==",
+        Token::GreaterEqual => ">=",
+        Token::LessEqual => "<=",
+        Token::Greater => ">",
+        Token::Less => "<",
+        Token::And => "and",
+        Token::Or => "or",
+        _ => unreachable!(),
+    }
+}
+
+/// Print `expr` in infix notation, parenthesised if it binds more loosely than `min_precedence`.
+fn print_operand(expr: &dyn Expr, min_precedence: u8) -> String {
+    if expr.precedence() < min_precedence {
+        format!("({})", expr.print())
+    } else {
+        expr.print()
+    }
+}
+
+/// How `AstPrinter` lays out a tree.
+pub enum Style {
+    /// The book's prefix notation, e.g. `(* (- 123) (group 45.67))`.
+    Lisp,
+    /// Conventional infix notation, e.g. `-123 * (45.67)`.
+    Infix,
+}
+
+pub struct AstPrinter {
+    style: Style,
+}
+
+impl AstPrinter {
+    pub fn new(style: Style) -> Self {
+        AstPrinter { style }
+    }
+
+    pub fn print(&self, expr: &dyn Expr) -> String {
+        match self.style {
+            Style::Lisp => expr.print_lisp(),
+            Style::Infix => expr.print(),
+        }
+    }
 }
 
-/// Rust compiler AST
-/// https://doc.rust-lang.org/beta/nightly-rustc/src/rustc_ast/ast.rs.html#1-3821
+// Rust compiler AST
+// https://doc.rust-lang.org/beta/nightly-rustc/src/rustc_ast/ast.rs.html#1-3821
 
 pub struct Binary {
     lhs: Box<dyn Expr>,
     op: Token,
     rhs: Box<dyn Expr>,
+    span: Option<Span>,
 }
 
 impl Binary {
     pub(crate) fn new(lhs: Box<dyn Expr>, op: Token, rhs: Box<dyn Expr>) -> Box<Self> {
-        Box::new(Binary { lhs, op, rhs })
+        Box::new(Binary {
+            lhs,
+            op,
+            rhs,
+            span: None,
+        })
+    }
+
+    /// This node, recorded as covering `span` from the start of `lhs` to the end of `rhs`.
+    pub(crate) fn with_span(mut self: Box<Self>, span: Span) -> Box<Self> {
+        self.span = Some(span);
+        self
     }
 }
 
 impl Expr for Binary {
     fn print(&self) -> String {
-        let op_str = match &self.op {
-            Token::Plus => "+",
-            Token::Minus => "-",
-            Token::Star => "*",
-            Token::Slash => "/",
-            _ => unreachable!(),
-        };
+        let precedence = self.precedence();
+
+        // Operators are left associative, so an equal-precedence rhs needs parentheses
+        format!(
+            "{} {} {}",
+            print_operand(self.lhs.as_ref(), precedence),
+            operator_str(&self.op),
+            print_operand(self.rhs.as_ref(), precedence + 1)
+        )
+    }
 
-        format!("{} {} {}", self.lhs.print(), op_str, self.rhs.print())
+    fn print_lisp(&self) -> String {
+        format!(
+            "({} {} {})",
+            operator_str(&self.op),
+            self.lhs.print_lisp(),
+            self.rhs.print_lisp()
+        )
     }
 
-    fn eval(&self) -> Box<dyn Expr> {
-        let lhs = self.lhs.eval();
-        let rhs = self.rhs.eval();
+    fn precedence(&self) -> u8 {
+        operator_precedence(&self.op)
+    }
+
+    fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    fn eval(&self, env: &mut Environment) -> Result<Value, RuntimeError> {
+        let lhs = self.lhs.eval(env)?;
+        let rhs = self.rhs.eval(env)?;
 
-        // If lhs and rhs are both NumberLiterals add
         match (lhs, rhs) {
-            (Expressions::Number(ref l_val), Expressions::Number(ref r_val)) => match self.op {
-                Token::Plus => NumberLiteral::new(l_val.value + r_val.value),
-                Token::Minus => NumberLiteral::new(l_val.value - r_val.value),
-                Token::Star => NumberLiteral::new(l_val.value * r_val.value),
-                Token::Slash => NumberLiteral::new(l_val.value / r_val.value),
-                _ => unreachable!(),
-            },
-            _ => unreachable!(),
+            (Value::Number(l_val), Value::Number(r_val)) => {
+                let result = match self.op {
+                    Token::Plus => l_val + r_val,
+                    Token::Minus => l_val - r_val,
+                    Token::Star => l_val * r_val,
+                    Token::Slash => l_val / r_val,
+                    _ => unreachable!(),
+                };
+                #[cfg(feature = "integer-mode")]
+                check_integer_result(l_val, r_val, result)?;
+                Ok(Value::Number(result))
+            }
+            (Value::Str(l_val), Value::Str(r_val)) if self.op == Token::Plus => {
+                Ok(Value::Str(l_val + &r_val))
+            }
+            _ if self.op == Token::Plus => Err(RuntimeError::new(
+                "Operands must be two numbers or two strings.",
+            )),
+            _ => Err(RuntimeError::new("Operands must be numbers.")),
         }
     }
 }
 
+/// Largest integer an f64 holds exactly, 2^53 - 1.
+#[cfg(feature = "integer-mode")]
+const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+
+/// In integer mode, arithmetic on two whole numbers must give a whole number no larger than
+/// `MAX_SAFE_INTEGER`. Other operands are left to ordinary float arithmetic.
+#[cfg(feature = "integer-mode")]
+fn check_integer_result(lhs: f64, rhs: f64, result: f64) -> Result<(), RuntimeError> {
+    let is_integer = |value: f64| value.is_finite() && value.fract() == 0.0;
+    if !is_integer(lhs) || !is_integer(rhs) {
+        return Ok(());
+    }
+
+    // Division by zero gives an infinity or NaN, which is no more an integer than 2.5
+    if !is_integer(result) {
+        Err(RuntimeError::new("Non-integer result in integer mode."))
+    } else if result.abs() > MAX_SAFE_INTEGER {
+        Err(RuntimeError::new("Integer overflow in integer mode."))
+    } else {
+        Ok(())
+    }
+}
+
 pub struct Grouping {
     expr: Box<dyn Expr>,
 }
 
 impl Grouping {
-    fn new(expr: Box<dyn Expr>) -> Box<Self> {
+    pub(crate) fn new(expr: Box<dyn Expr>) -> Box<Self> {
         Box::new(Grouping { expr })
     }
 }
@@ -66,8 +214,12 @@ impl Expr for Grouping {
         format!("({})", self.expr.print())
     }
 
-    fn eval(&self) -> Box<dyn Expr> {
-        self.expr.eval()
+    fn print_lisp(&self) -> String {
+        format!("(group {})", self.expr.print_lisp())
+    }
+
+    fn eval(&self, env: &mut Environment) -> Result<Value, RuntimeError> {
+        self.expr.eval(env)
     }
 }
 
@@ -75,17 +227,34 @@ pub struct StringLiteral {
     value: String,
 }
 impl StringLiteral {
-    fn new(value: String) -> Box<Self> {
+    pub(crate) fn new(value: String) -> Box<Self> {
         Box::new(StringLiteral { value })
     }
 }
 
 impl Expr for StringLiteral {
+    /// Quoted and re-escaped, so a string can't be mistaken for a variable of the same name and
+    /// prints as source the scanner would read back.
     fn print(&self) -> String {
+        let mut quoted = String::from('"');
+        for c in self.value.chars() {
+            match c {
+                '\n' => quoted.push_str("\\n"),
+                '\t' => quoted.push_str("\\t"),
+                '\r' => quoted.push_str("\\r"),
+                '\\' => quoted.push_str("\\\\"),
+                '"' => quoted.push_str("\\\""),
+                _ => quoted.push(c),
+            }
+        }
+        quoted.push('"');
+        quoted
+    }
+    fn print_lisp(&self) -> String {
         self.value.clone()
     }
-    fn eval(&self) -> Box<dyn Expr> {
-        Self::new(self.value.clone())
+    fn eval(&self, _env: &mut Environment) -> Result<Value, RuntimeError> {
+        Ok(Value::Str(self.value.clone()))
     }
 }
 
@@ -100,10 +269,13 @@ impl NumberLiteral {
 
 impl Expr for NumberLiteral {
     fn print(&self) -> String {
-        self.value.to_string()
+        format_number(self.value)
+    }
+    fn print_lisp(&self) -> String {
+        self.print()
     }
-    fn eval(&self) -> Box<dyn Expr> {
-        NumberLiteral::new(self.value)
+    fn eval(&self, _env: &mut Environment) -> Result<Value, RuntimeError> {
+        Ok(Value::Number(self.value))
     }
 }
 
@@ -119,8 +291,31 @@ impl Expr for BooleanLiteral {
     fn print(&self) -> String {
         self.value.to_string()
     }
-    fn eval(&self) -> Box<dyn Expr> {
-        BooleanLiteral::new(self.value)
+    fn print_lisp(&self) -> String {
+        self.print()
+    }
+    fn eval(&self, _env: &mut Environment) -> Result<Value, RuntimeError> {
+        Ok(Value::Bool(self.value))
+    }
+}
+
+pub struct NilLiteral;
+
+impl NilLiteral {
+    pub(crate) fn new() -> Box<Self> {
+        Box::new(NilLiteral)
+    }
+}
+
+impl Expr for NilLiteral {
+    fn print(&self) -> String {
+        "nil".to_string()
+    }
+    fn print_lisp(&self) -> String {
+        self.print()
+    }
+    fn eval(&self, _env: &mut Environment) -> Result<Value, RuntimeError> {
+        Ok(Value::Nil)
     }
 }
 
@@ -131,7 +326,7 @@ pub struct Logical {
 }
 
 impl Logical {
-    fn new(left: Box<dyn Expr>, operator: Token, right: Box<dyn Expr>) -> Box<Self> {
+    pub(crate) fn new(left: Box<dyn Expr>, operator: Token, right: Box<dyn Expr>) -> Box<Self> {
         Box::new(Logical {
             left,
             operator,
@@ -142,110 +337,178 @@ impl Logical {
 
 impl Expr for Logical {
     fn print(&self) -> String {
-        let op_str = match &self.operator {
-            Token::EqualEqual => "==",
-            Token::GreaterEqual => ">=",
-            Token::LessEqual => "<=",
-            Token::Greater => ">",
-            Token::Less => "<",
-            _ => unreachable!(),
-        };
+        let precedence = self.precedence();
+
+        format!(
+            "{} {} {}",
+            print_operand(self.left.as_ref(), precedence),
+            operator_str(&self.operator),
+            print_operand(self.right.as_ref(), precedence + 1)
+        )
+    }
+    fn print_lisp(&self) -> String {
+        format!(
+            "({} {} {})",
+            operator_str(&self.operator),
+            self.left.print_lisp(),
+            self.right.print_lisp()
+        )
+    }
+    fn precedence(&self) -> u8 {
+        operator_precedence(&self.operator)
+    }
+    fn eval(&self, env: &mut Environment) -> Result<Value, RuntimeError> {
+        let left = self.left.eval(env)?;
+
+        // `and`/`or` yield whichever operand decided the result, skipping the right if possible
+        match self.operator {
+            Token::Or if left.is_truthy() => return Ok(left),
+            Token::And if !left.is_truthy() => return Ok(left),
+            Token::Or | Token::And => return self.right.eval(env),
+            _ => {}
+        }
 
-        format!("{} {:?} {}", self.left.print(), op_str, self.right.print())
-    }
-    fn eval(&self) -> Box<dyn Expr> {
-        let left = self.left.eval();
-        let right = self.right.eval();
-
-        match (left, right) {
-            (Expressions::Number(ref l_val), Expressions::Number(ref r_val)) => {
-                match self.operator {
-                    Token::BangEqual => BooleanLiteral::new(l_val.value != r_val.value),
-                    Token::EqualEqual => BooleanLiteral::new(l_val.value == r_val.value),
-                    Token::Greater => BooleanLiteral::new(l_val.value > r_val.value),
-                    Token::GreaterEqual => BooleanLiteral::new(l_val.value >= r_val.value),
-                    Token::Less => BooleanLiteral::new(l_val.value <= r_val.value),
-                    Token::LessEqual => BooleanLiteral::new(l_val.value <= r_val.value),
-                    _ => unreachable!(),
-                }
-            }
-            _ => unreachable!(),
+        let right = self.right.eval(env)?;
+
+        match self.operator {
+            // Any two values can be compared for equality
+            Token::BangEqual => return Ok(Value::Bool(left != right)),
+            Token::EqualEqual => return Ok(Value::Bool(left == right)),
+            _ => {}
         }
+
+        let ordering = match (left, right) {
+            (Value::Number(l_val), Value::Number(r_val)) => l_val.partial_cmp(&r_val),
+            (Value::Str(l_val), Value::Str(r_val)) => Some(l_val.cmp(&r_val)),
+            _ => {
+                return Err(RuntimeError::new(
+                    "Operands must be two numbers or two strings.",
+                ));
+            }
+        };
+
+        // NaN is unordered, so every comparison against it is false
+        Ok(Value::Bool(ordering.is_some_and(
+            |ordering| match self.operator {
+                Token::Greater => ordering.is_gt(),
+                Token::GreaterEqual => ordering.is_ge(),
+                Token::Less => ordering.is_lt(),
+                Token::LessEqual => ordering.is_le(),
+                _ => unreachable!(),
+            },
+        )))
     }
 }
 
-struct Unary {
+pub struct Unary {
     operator: Token,
     right: Box<dyn Expr>,
 }
 
 impl Unary {
-    fn new(operator: Token, right: Box<dyn Expr>) -> Box<Self> {
+    pub(crate) fn new(operator: Token, right: Box<dyn Expr>) -> Box<Self> {
         Box::new(Unary { operator, right })
     }
 }
 
 impl Expr for Unary {
     fn print(&self) -> String {
-        let op_str = match &self.operator {
-            Token::Bang => "!",
-            Token::Minus => "-",
-            _ => unreachable!(),
-        };
-        format!("{} {}", op_str, self.right.print())
+        format!(
+            "{}{}",
+            operator_str(&self.operator),
+            print_operand(self.right.as_ref(), UNARY_PRECEDENCE)
+        )
     }
-    fn eval(&self) -> Box<dyn Expr> {
-        let right = self.right.eval();
+    fn print_lisp(&self) -> String {
+        format!(
+            "({} {})",
+            operator_str(&self.operator),
+            self.right.print_lisp()
+        )
+    }
+    fn precedence(&self) -> u8 {
+        UNARY_PRECEDENCE
+    }
+    fn eval(&self, env: &mut Environment) -> Result<Value, RuntimeError> {
+        let right = self.right.eval(env)?;
 
         match &self.operator {
-            Token::Minus => {
-                if let Ok(num_right) = right.downcast::<NumberLiteral>() {
-                    return NumberLiteral::new(-num_right.value);
-                }
-            }
-            Token::Bang => {
-                if let Ok(bool_right) = right.downcast::<BooleanLiteral>() {
-                    return BooleanLiteral::new(!bool_right.value);
-                }
-            }
-            _ => {}
+            Token::Minus => match right {
+                Value::Number(value) => Ok(Value::Number(-value)),
+                _ => Err(RuntimeError::new("Operand must be a number.")),
+            },
+            Token::Bang => Ok(Value::Bool(!right.is_truthy())),
+            _ => unreachable!("Unsupported unary operation"),
         }
+    }
+}
+
+pub struct Variable {
+    name: Token,
+}
+
+impl Variable {
+    pub(crate) fn new(name: Token) -> Box<Self> {
+        Box::new(Variable { name })
+    }
+}
 
-        unreachable!("Unsupported unary operation")
+/// The name held by an identifier token.
+fn identifier_name(token: &Token) -> &str {
+    match token {
+        Token::Identifier(name) => name,
+        _ => unreachable!(),
     }
 }
 
-struct Variable {
+impl Expr for Variable {
+    fn print(&self) -> String {
+        identifier_name(&self.name).to_owned()
+    }
+    fn print_lisp(&self) -> String {
+        self.print()
+    }
+    fn eval(&self, env: &mut Environment) -> Result<Value, RuntimeError> {
+        env.get(identifier_name(&self.name))
+    }
+}
+
+pub struct Assign {
     name: Token,
+    value: Box<dyn Expr>,
 }
 
-// impl Variable {
-//     fn new(name: Token) -> Box<Self> {
-//         Box::new(Variable { name })
-//     }
-// }
-//
-// impl Expr for Variable {
-//     fn print(&self) -> String {
-//         match &self.name {
-//             Token::Identifier(thing) => thing.to_owned(),
-//             _ => unreachable!(),
-//         }
-//     }
-// }
-
-enum Expressions {
-    // Call(Box<Call>),
-    Grouping(Box<Grouping>),
-    StringLiteral(Box<StringLiteral>),
-    Number(Box<NumberLiteral>),
-    Boolean(Box<BooleanLiteral>),
-    Logical(Box<Logical>),
+impl Assign {
+    pub(crate) fn new(name: Token, value: Box<dyn Expr>) -> Box<Self> {
+        Box::new(Assign { name, value })
+    }
+}
+
+impl Expr for Assign {
+    fn print(&self) -> String {
+        format!("{} = {}", identifier_name(&self.name), self.value.print())
+    }
+    fn print_lisp(&self) -> String {
+        format!(
+            "(= {} {})",
+            identifier_name(&self.name),
+            self.value.print_lisp()
+        )
+    }
+    fn eval(&self, env: &mut Environment) -> Result<Value, RuntimeError> {
+        let value = self.value.eval(env)?;
+        env.assign(identifier_name(&self.name), value.clone())?;
+        Ok(value)
+    }
+    fn precedence(&self) -> u8 {
+        ASSIGNMENT_PRECEDENCE
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tokens::scan_line;
 
     #[test]
     fn test_tree() {
@@ -264,4 +527,316 @@ mod tests {
 
         println!("{}", tree.print());
     }
+
+    #[test]
+    // `1 / 2` is an error in integer mode
+    #[cfg(not(feature = "integer-mode"))]
+    fn test_eval_arithmetic() {
+        // (1/2) - 2
+        let tree = Binary::new(
+            Grouping::new(Binary::new(
+                NumberLiteral::new(1.0),
+                Token::Slash,
+                NumberLiteral::new(2.0),
+            )),
+            Token::Minus,
+            NumberLiteral::new(2.0),
+        );
+        assert_eq!(tree.eval(&mut Environment::new()), Ok(Value::Number(-1.5)));
+    }
+
+    #[test]
+    fn test_eval_negate_string() {
+        let tree = Unary::new(Token::Minus, StringLiteral::new("hi".to_string()));
+        assert_eq!(
+            tree.eval(&mut Environment::new()),
+            Err(RuntimeError::new("Operand must be a number."))
+        );
+    }
+
+    /// `lhs op rhs` evaluated in a fresh environment.
+    fn eval_binary(lhs: f64, op: Token, rhs: f64) -> Result<Value, RuntimeError> {
+        Binary::new(NumberLiteral::new(lhs), op, NumberLiteral::new(rhs))
+            .eval(&mut Environment::new())
+    }
+
+    #[test]
+    #[cfg(not(feature = "integer-mode"))]
+    fn test_eval_float_division() {
+        assert_eq!(eval_binary(5.0, Token::Slash, 2.0), Ok(Value::Number(2.5)));
+    }
+
+    #[test]
+    #[cfg(feature = "integer-mode")]
+    fn test_eval_integer_mode() {
+        assert_eq!(
+            eval_binary(5.0, Token::Slash, 2.0),
+            Err(RuntimeError::new("Non-integer result in integer mode."))
+        );
+        assert_eq!(eval_binary(4.0, Token::Slash, 2.0), Ok(Value::Number(2.0)));
+        assert_eq!(eval_binary(2.0, Token::Plus, 3.0), Ok(Value::Number(5.0)));
+        assert_eq!(
+            eval_binary(MAX_SAFE_INTEGER, Token::Plus, 1.0),
+            Err(RuntimeError::new("Integer overflow in integer mode."))
+        );
+
+        // Non-integer operands still use float arithmetic
+        assert_eq!(eval_binary(1.5, Token::Star, 3.0), Ok(Value::Number(4.5)));
+    }
+
+    #[test]
+    fn test_eval_concatenate() {
+        let tree = Binary::new(
+            StringLiteral::new("foo".to_string()),
+            Token::Plus,
+            StringLiteral::new("bar".to_string()),
+        );
+        assert_eq!(
+            tree.eval(&mut Environment::new()),
+            Ok(Value::Str("foobar".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_eval_mixed_operands() {
+        let tree = Binary::new(
+            StringLiteral::new("foo".to_string()),
+            Token::Plus,
+            NumberLiteral::new(1.0),
+        );
+        assert_eq!(
+            tree.eval(&mut Environment::new()),
+            Err(RuntimeError::new(
+                "Operands must be two numbers or two strings."
+            ))
+        );
+
+        let tree = Binary::new(
+            StringLiteral::new("foo".to_string()),
+            Token::Star,
+            NumberLiteral::new(2.0),
+        );
+        assert_eq!(
+            tree.eval(&mut Environment::new()),
+            Err(RuntimeError::new("Operands must be numbers."))
+        );
+    }
+
+    #[test]
+    fn test_eval_comparison() {
+        let tree = Logical::new(
+            NumberLiteral::new(1.0),
+            Token::LessEqual,
+            NumberLiteral::new(2.0),
+        );
+        assert_eq!(tree.eval(&mut Environment::new()), Ok(Value::Bool(true)));
+
+        let tree = Logical::new(
+            StringLiteral::new("a".to_string()),
+            Token::EqualEqual,
+            NilLiteral::new(),
+        );
+        assert_eq!(tree.eval(&mut Environment::new()), Ok(Value::Bool(false)));
+
+        let tree = Unary::new(Token::Bang, NilLiteral::new());
+        assert_eq!(tree.eval(&mut Environment::new()), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_eval_strict_less() {
+        let tree = Logical::new(
+            NumberLiteral::new(1.0),
+            Token::Less,
+            NumberLiteral::new(2.0),
+        );
+        assert_eq!(tree.eval(&mut Environment::new()), Ok(Value::Bool(true)));
+
+        let tree = Logical::new(
+            NumberLiteral::new(2.0),
+            Token::Less,
+            NumberLiteral::new(2.0),
+        );
+        assert_eq!(tree.eval(&mut Environment::new()), Ok(Value::Bool(false)));
+
+        let tree = Logical::new(
+            StringLiteral::new("apple".to_string()),
+            Token::Less,
+            StringLiteral::new("banana".to_string()),
+        );
+        assert_eq!(tree.eval(&mut Environment::new()), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_eval_and_or() {
+        let tree = Logical::new(
+            BooleanLiteral::new(false),
+            Token::Or,
+            StringLiteral::new("x".to_string()),
+        );
+        assert_eq!(
+            tree.eval(&mut Environment::new()),
+            Ok(Value::Str("x".to_string()))
+        );
+
+        // The undefined rhs is never evaluated
+        let tree = Logical::new(
+            NilLiteral::new(),
+            Token::And,
+            Variable::new(Token::Identifier("whatever".to_string())),
+        );
+        assert_eq!(tree.eval(&mut Environment::new()), Ok(Value::Nil));
+    }
+
+    #[test]
+    fn test_eval_variable() {
+        // var x = 5; x + 1
+        let mut env = Environment::new();
+        env.define("x", Value::Number(5.0)).unwrap();
+
+        let tree = Binary::new(
+            Variable::new(Token::Identifier("x".to_string())),
+            Token::Plus,
+            NumberLiteral::new(1.0),
+        );
+        assert_eq!(tree.eval(&mut env), Ok(Value::Number(6.0)));
+    }
+
+    #[test]
+    fn test_eval_undefined_variable() {
+        let tree = Variable::new(Token::Identifier("missing".to_string()));
+        assert_eq!(
+            tree.eval(&mut Environment::new()),
+            Err(RuntimeError::new("Undefined variable 'missing'."))
+        );
+    }
+
+    #[test]
+    fn test_eval_assign() {
+        let mut env = Environment::new();
+        env.define("x", Value::Nil).unwrap();
+
+        let tree = Assign::new(Token::Identifier("x".to_string()), NumberLiteral::new(2.0));
+        assert_eq!(tree.eval(&mut env), Ok(Value::Number(2.0)));
+        assert_eq!(env.get("x"), Ok(Value::Number(2.0)));
+    }
+
+    #[test]
+    fn test_grouping_from_tokens() {
+        let (_, tokens) = scan_line("(1 + 2)").unwrap();
+        let tokens: Vec<Token> = tokens.into_iter().map(|located| located.token).collect();
+
+        let tree = match tokens.as_slice() {
+            [
+                Token::LeftParen,
+                Token::Number(lhs),
+                Token::Plus,
+                Token::Number(rhs),
+                Token::RightParen,
+            ] => Grouping::new(Binary::new(
+                NumberLiteral::new(*lhs),
+                Token::Plus,
+                NumberLiteral::new(*rhs),
+            )),
+            _ => panic!("unexpected tokens {:?}", tokens),
+        };
+
+        assert_eq!(tree.print_lisp(), "(group (+ 1 2))");
+    }
+
+    #[test]
+    fn test_printer_styles() {
+        // -123 * (45.67)
+        let tree = Binary::new(
+            Unary::new(Token::Minus, NumberLiteral::new(123.0)),
+            Token::Star,
+            Grouping::new(NumberLiteral::new(45.67)),
+        );
+
+        let lisp = AstPrinter::new(Style::Lisp);
+        let infix = AstPrinter::new(Style::Infix);
+        assert_eq!(lisp.print(tree.as_ref()), "(* (- 123) (group 45.67))");
+        assert_eq!(infix.print(tree.as_ref()), "-123 * (45.67)");
+        // Infix quotes strings, Lisp keeps the book's bare form
+        let tree = Logical::new(
+            StringLiteral::new("hi".to_string()),
+            Token::EqualEqual,
+            NilLiteral::new(),
+        );
+        assert_eq!(lisp.print(tree.as_ref()), "(== hi nil)");
+        assert_eq!(infix.print(tree.as_ref()), "\"hi\" == nil");
+
+        // Escapes are written back out, so an embedded quote can't end the string early
+        let tree = StringLiteral::new("a\"b\\c\nd".to_string());
+        assert_eq!(infix.print(tree.as_ref()), "\"a\\\"b\\\\c\\nd\"");
+    }
+
+    #[test]
+    fn test_infix_parenthesises_by_precedence() {
+        // (1 + 2) * 3, built without a Grouping node
+        let tree = Binary::new(
+            Binary::new(
+                NumberLiteral::new(1.0),
+                Token::Plus,
+                NumberLiteral::new(2.0),
+            ),
+            Token::Star,
+            NumberLiteral::new(3.0),
+        );
+        assert_eq!(
+            AstPrinter::new(Style::Lisp).print(tree.as_ref()),
+            "(* (+ 1 2) 3)"
+        );
+        assert_eq!(
+            AstPrinter::new(Style::Infix).print(tree.as_ref()),
+            "(1 + 2) * 3"
+        );
+
+        // 1 - (2 - 3) keeps its parentheses, 1 + 2 * 3 needs none
+        let tree = Binary::new(
+            NumberLiteral::new(1.0),
+            Token::Minus,
+            Binary::new(
+                NumberLiteral::new(2.0),
+                Token::Minus,
+                NumberLiteral::new(3.0),
+            ),
+        );
+        assert_eq!(
+            AstPrinter::new(Style::Infix).print(tree.as_ref()),
+            "1 - (2 - 3)"
+        );
+
+        let tree = Binary::new(
+            NumberLiteral::new(1.0),
+            Token::Plus,
+            Binary::new(
+                NumberLiteral::new(2.0),
+                Token::Star,
+                NumberLiteral::new(3.0),
+            ),
+        );
+        assert_eq!(
+            AstPrinter::new(Style::Infix).print(tree.as_ref()),
+            "1 + 2 * 3"
+        );
+
+        // Comparisons bind more loosely than arithmetic
+        let tree = Logical::new(
+            BooleanLiteral::new(true),
+            Token::EqualEqual,
+            Logical::new(
+                NumberLiteral::new(1.0),
+                Token::Less,
+                NumberLiteral::new(2.0),
+            ),
+        );
+        assert_eq!(
+            AstPrinter::new(Style::Lisp).print(tree.as_ref()),
+            "(== true (< 1 2))"
+        );
+        assert_eq!(
+            AstPrinter::new(Style::Infix).print(tree.as_ref()),
+            "true == 1 < 2"
+        );
+    }
 }
@@ -0,0 +1,174 @@
+use crate::value::{RuntimeError, Value};
+use std::collections::HashMap;
+
+/// Variable bindings for one scope, with a link to the scope enclosing it.
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: HashMap<String, Value>,
+    enclosing: Option<Box<Environment>>,
+    /// Set by `freeze`, after which this scope's bindings can't be changed or added to.
+    frozen: bool,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Environment::default()
+    }
+
+    /// A new, empty scope nested inside `enclosing`.
+    pub fn new_enclosed(enclosing: Environment) -> Self {
+        Environment {
+            values: HashMap::new(),
+            enclosing: Some(Box::new(enclosing)),
+            frozen: false,
+        }
+    }
+
+    /// Make this scope read-only, such as a preamble of globals. Scopes later nested inside it
+    /// with `new_enclosed` are still mutable.
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+    }
+
+    /// Discard this scope, returning the one it was nested in.
+    pub fn into_enclosing(self) -> Option<Environment> {
+        self.enclosing.map(|enclosing| *enclosing)
+    }
+
+    /// Bind `name` in this scope, replacing any existing binding here. Fails if the scope is
+    /// frozen.
+    pub fn define(&mut self, name: &str, value: Value) -> Result<(), RuntimeError> {
+        if self.frozen {
+            return Err(frozen());
+        }
+        self.values.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    /// Look `name` up in this scope, then each enclosing scope in turn.
+    pub fn get(&self, name: &str) -> Result<Value, RuntimeError> {
+        match self.values.get(name) {
+            Some(value) => Ok(value.clone()),
+            None => match &self.enclosing {
+                Some(enclosing) => enclosing.get(name),
+                None => Err(undefined(name)),
+            },
+        }
+    }
+
+    /// Whether `name` is bound in this scope or any enclosing one, without cloning its value.
+    pub fn contains(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+            || self
+                .enclosing
+                .as_ref()
+                .is_some_and(|enclosing| enclosing.contains(name))
+    }
+
+    /// Rebind the nearest existing `name`. Unlike `define`, this never creates a binding.
+    pub fn assign(&mut self, name: &str, value: Value) -> Result<(), RuntimeError> {
+        match self.values.get_mut(name) {
+            Some(_) if self.frozen => Err(frozen()),
+            Some(slot) => {
+                *slot = value;
+                Ok(())
+            }
+            None => match &mut self.enclosing {
+                Some(enclosing) => enclosing.assign(name, value),
+                None => Err(undefined(name)),
+            },
+        }
+    }
+}
+
+fn undefined(name: &str) -> RuntimeError {
+    RuntimeError::new(&format!("Undefined variable '{}'.", name))
+}
+
+fn frozen() -> RuntimeError {
+    RuntimeError::new("Cannot modify frozen binding.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_define_and_get() {
+        let mut env = Environment::new();
+        env.define("x", Value::Number(5.0)).unwrap();
+        assert_eq!(env.get("x"), Ok(Value::Number(5.0)));
+    }
+
+    #[test]
+    fn test_undefined() {
+        let mut env = Environment::new();
+        assert_eq!(
+            env.get("y"),
+            Err(RuntimeError::new("Undefined variable 'y'."))
+        );
+        assert_eq!(
+            env.assign("y", Value::Nil),
+            Err(RuntimeError::new("Undefined variable 'y'."))
+        );
+    }
+
+    #[test]
+    fn test_enclosing_scopes() {
+        let mut globals = Environment::new();
+        globals.define("a", Value::Number(1.0)).unwrap();
+        globals.define("b", Value::Number(2.0)).unwrap();
+
+        let mut local = Environment::new_enclosed(globals);
+        local.define("a", Value::Str("shadow".to_string())).unwrap();
+        assert_eq!(local.get("a"), Ok(Value::Str("shadow".to_string())));
+        assert_eq!(local.get("b"), Ok(Value::Number(2.0)));
+
+        // Assignment reaches through to the enclosing scope
+        local.assign("b", Value::Number(3.0)).unwrap();
+
+        let globals = local.into_enclosing().unwrap();
+        assert_eq!(globals.get("a"), Ok(Value::Number(1.0)));
+        assert_eq!(globals.get("b"), Ok(Value::Number(3.0)));
+    }
+
+    #[test]
+    fn test_contains() {
+        let mut globals = Environment::new();
+        globals.define("global", Value::Nil).unwrap();
+        globals.define("shadowed", Value::Number(1.0)).unwrap();
+
+        let mut local = Environment::new_enclosed(globals);
+        local.define("local", Value::Bool(false)).unwrap();
+        local.define("shadowed", Value::Number(2.0)).unwrap();
+
+        assert!(local.contains("local"));
+        assert!(local.contains("global"));
+        assert!(!local.contains("undeclared"));
+
+        // Shadowing leaves the outer binding in place
+        assert!(local.contains("shadowed"));
+        let globals = local.into_enclosing().unwrap();
+        assert!(globals.contains("shadowed"));
+        assert!(!globals.contains("local"));
+    }
+
+    #[test]
+    fn test_freeze() {
+        let mut globals = Environment::new();
+        globals.define("PI", Value::Number(3.0)).unwrap();
+        globals.freeze();
+
+        let frozen = Err(RuntimeError::new("Cannot modify frozen binding."));
+        assert_eq!(globals.assign("PI", Value::Number(4.0)), frozen);
+        assert_eq!(globals.define("E", Value::Number(2.0)), frozen);
+
+        // A child scope can add locals, but still can't reassign the frozen one
+        let mut local = Environment::new_enclosed(globals);
+        local.define("r", Value::Number(2.0)).unwrap();
+        assert_eq!(local.get("r"), Ok(Value::Number(2.0)));
+        assert_eq!(local.assign("PI", Value::Nil), frozen);
+
+        assert_eq!(local.get("PI"), Ok(Value::Number(3.0)));
+    }
+}
@@ -0,0 +1,179 @@
+/// Format a Lox number the way the reference (Java) implementation prints it.
+///
+/// Infinities and NaN are spelled `Infinity`, `-Infinity` and `NaN` rather than Rust's
+/// `inf`/`NaN`, so output can be compared directly against reference Lox.
+pub fn format_number(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value.is_sign_positive() {
+            "Infinity".to_string()
+        } else {
+            "-Infinity".to_string()
+        }
+    } else {
+        value.to_string()
+    }
+}
+
+/// Output-only formatting preferences for numbers. Scanning always uses `.`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrintOptions {
+    /// Separates the whole and fractional parts, `.` by default.
+    decimal_separator: char,
+    /// Inserted between groups of three whole-part digits, if set.
+    thousands_separator: Option<char>,
+}
+
+/// Why a `PrintOptions` was rejected.
+#[derive(Debug, PartialEq)]
+pub struct PrintOptionsError {
+    pub message: String,
+}
+
+impl PrintOptions {
+    /// Separators for printed numbers. They must differ, or `1.234` could mean either a
+    /// thousand and some or just over one, and neither may be a digit or `-`, which already
+    /// appear in numbers.
+    pub fn new(
+        decimal_separator: char,
+        thousands_separator: Option<char>,
+    ) -> Result<Self, PrintOptionsError> {
+        for separator in [Some(decimal_separator), thousands_separator]
+            .into_iter()
+            .flatten()
+        {
+            if separator.is_ascii_digit() || separator == '-' {
+                return Err(PrintOptionsError {
+                    message: format!("'{}' can't be used as a separator.", separator),
+                });
+            }
+        }
+        if thousands_separator == Some(decimal_separator) {
+            return Err(PrintOptionsError {
+                message: format!(
+                    "Decimal and thousands separators must differ, both are '{}'.",
+                    decimal_separator
+                ),
+            });
+        }
+
+        Ok(PrintOptions {
+            decimal_separator,
+            thousands_separator,
+        })
+    }
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        PrintOptions {
+            decimal_separator: '.',
+            thousands_separator: None,
+        }
+    }
+}
+
+/// `format_number` with the separators in `options` applied.
+pub fn format_number_with_options(value: f64, options: &PrintOptions) -> String {
+    let formatted = format_number(value);
+    if !value.is_finite() {
+        return formatted;
+    }
+
+    let (sign, digits) = match formatted.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", formatted.as_str()),
+    };
+    let (whole, fraction) = match digits.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (digits, None),
+    };
+
+    let mut result = sign.to_string();
+    for (index, digit) in whole.chars().enumerate() {
+        if let Some(separator) = options.thousands_separator
+            && index > 0
+            && (whole.len() - index) % 3 == 0
+        {
+            result.push(separator);
+        }
+        result.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        result.push(options.decimal_separator);
+        result.push_str(fraction);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_finite() {
+        assert_eq!(format_number(10.0), "10");
+        assert_eq!(format_number(1.5), "1.5");
+    }
+
+    #[test]
+    fn test_format_non_finite() {
+        assert_eq!(format_number(f64::INFINITY), "Infinity");
+        assert_eq!(format_number(f64::NEG_INFINITY), "-Infinity");
+        assert_eq!(format_number(f64::NAN), "NaN");
+    }
+
+    #[test]
+    // 3.14 is the value being formatted, not an approximation of PI
+    #[allow(clippy::approx_constant)]
+    fn test_decimal_separator() {
+        let options = PrintOptions::new(',', None).unwrap();
+        assert_eq!(format_number_with_options(3.25, &options), "3,25");
+        assert_eq!(format_number_with_options(3.14, &options), "3,14");
+        assert_eq!(format_number_with_options(10.0, &options), "10");
+    }
+
+    #[test]
+    fn test_decimal_and_thousands_separators() {
+        let options = PrintOptions::new(',', Some('.')).unwrap();
+        assert_eq!(format_number_with_options(1234.5, &options), "1.234,5");
+        assert_eq!(
+            format_number_with_options(-1234567.0, &options),
+            "-1.234.567"
+        );
+        assert_eq!(format_number_with_options(123.0, &options), "123");
+        assert_eq!(
+            format_number_with_options(f64::INFINITY, &options),
+            "Infinity"
+        );
+    }
+
+    #[test]
+    fn test_matching_separators_are_rejected() {
+        assert_eq!(
+            PrintOptions::new('.', Some('.')),
+            Err(PrintOptionsError {
+                message: "Decimal and thousands separators must differ, both are '.'.".to_string()
+            })
+        );
+
+        // Digits and `-` would run into the number itself, e.g. `-1.5` as `-1-5`
+        let invalid = |separator| {
+            Err(PrintOptionsError {
+                message: format!("'{}' can't be used as a separator.", separator),
+            })
+        };
+        assert_eq!(PrintOptions::new('-', None), invalid('-'));
+        assert_eq!(PrintOptions::new('5', None), invalid('5'));
+        assert_eq!(PrintOptions::new(',', Some('-')), invalid('-'));
+        assert_eq!(PrintOptions::new(',', Some('0')), invalid('0'));
+    }
+
+    #[test]
+    fn test_default_print_options() {
+        let options = PrintOptions::default();
+        assert_eq!(format_number_with_options(1234.5, &options), "1234.5");
+    }
+}
@@ -0,0 +1,84 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Lines entered at the REPL, persisted to a file so they survive between sessions.
+pub struct History {
+    path: PathBuf,
+    entries: Vec<String>,
+}
+
+impl History {
+    /// Load the history stored at `path`. A missing or unreadable file starts an empty history.
+    pub fn load(path: PathBuf) -> Self {
+        let entries = std::fs::read_to_string(&path)
+            .map(|contents| contents.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+
+        History { path, entries }
+    }
+
+    /// The default history file, `~/.rlox_history`.
+    pub fn default_path() -> Option<PathBuf> {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".rlox_history"))
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Record `line` and append it to the history file. Failing to write the file only costs
+    /// the history, so it's reported rather than treated as fatal.
+    pub fn append(&mut self, line: &str) {
+        let result = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| writeln!(file, "{}", line));
+
+        if let Err(err) = result {
+            eprintln!(
+                "Could not write history to {}: {}",
+                self.path.display(),
+                err
+            );
+        }
+
+        self.entries.push(line.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_history_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rlox_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_missing_history_is_empty() {
+        let history = History::load(temp_history_path("missing"));
+        assert!(history.entries().is_empty());
+    }
+
+    #[test]
+    fn test_history_round_trip() {
+        let path = temp_history_path("round_trip");
+
+        let mut history = History::load(path.clone());
+        history.append("var x = 1;");
+        history.append("print x;");
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "var x = 1;\nprint x;\n"
+        );
+
+        let history = History::load(path.clone());
+        assert_eq!(history.entries(), ["var x = 1;", "print x;"]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
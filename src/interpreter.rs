@@ -0,0 +1,140 @@
+use crate::environment::Environment;
+use crate::format::PrintOptions;
+use crate::stmt::Stmt;
+use crate::tokens::Token;
+use crate::value::{RuntimeError, Value};
+use std::io::Write;
+
+/// Execute `stmts` in order against `env`, writing `print` output to stdout. Stops at the first
+/// runtime error.
+pub fn interpret(stmts: Vec<Stmt>, env: &mut Environment) -> Result<(), RuntimeError> {
+    interpret_to(stmts, env, &mut std::io::stdout(), &PrintOptions::default())
+}
+
+/// `interpret`, writing `print` output to `out` instead of stdout and formatting numbers with
+/// `options`.
+pub fn interpret_to(
+    stmts: Vec<Stmt>,
+    env: &mut Environment,
+    out: &mut impl Write,
+    options: &PrintOptions,
+) -> Result<(), RuntimeError> {
+    let mut print = |value: Value| write_value(out, &value, options);
+    for stmt in stmts {
+        execute(&stmt, env, &mut print)?;
+    }
+    Ok(())
+}
+
+/// `interpret`, collecting the values `print` statements produce instead of writing them.
+pub fn interpret_values(
+    stmts: Vec<Stmt>,
+    env: &mut Environment,
+) -> Result<Vec<Value>, RuntimeError> {
+    let mut values = Vec::new();
+    let mut print = |value: Value| {
+        values.push(value);
+        Ok(())
+    };
+    for stmt in stmts {
+        execute(&stmt, env, &mut print)?;
+    }
+    Ok(values)
+}
+
+/// Write `value` to `out` on its own line, the way `print` shows it.
+pub(crate) fn write_value(
+    out: &mut impl Write,
+    value: &Value,
+    options: &PrintOptions,
+) -> Result<(), RuntimeError> {
+    writeln!(out, "{}", value.to_string_with(options))
+        .map_err(|err| RuntimeError::new(&format!("Could not print: {}.", err)))
+}
+
+/// Run a single statement, handing each value it prints to `print`.
+fn execute(
+    stmt: &Stmt,
+    env: &mut Environment,
+    print: &mut impl FnMut(Value) -> Result<(), RuntimeError>,
+) -> Result<(), RuntimeError> {
+    match stmt {
+        Stmt::Print(expr) => print(expr.eval(env)?)?,
+        Stmt::Expression(expr) => {
+            expr.eval(env)?;
+        }
+        Stmt::Var(name, initializer) => {
+            let value = match initializer {
+                Some(expr) => expr.eval(env)?,
+                None => Value::Nil,
+            };
+            match name {
+                Token::Identifier(name) => env.define(name, value)?,
+                _ => unreachable!(),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_program;
+    use crate::tokens::scan_lines;
+
+    /// Scan, parse and run `source` in a fresh environment, returning what it printed.
+    fn run(source: &str) -> Result<String, RuntimeError> {
+        let tokens = scan_lines(source).unwrap().into_iter().flatten().collect();
+        let stmts = parse_program(tokens).unwrap();
+
+        let mut out = Vec::new();
+        interpret_to(
+            stmts,
+            &mut Environment::new(),
+            &mut out,
+            &PrintOptions::default(),
+        )?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_print() {
+        assert_eq!(run("print 1 + 2;"), Ok("3\n".to_string()));
+        assert_eq!(run("print \"hi\"; print nil;"), Ok("hi\nnil\n".to_string()));
+    }
+
+    #[test]
+    fn test_variables() {
+        let source = "var a = 1;\nvar b;\nb = a + 1;\nprint a + b;\nprint b = 5;\nprint b;";
+        assert_eq!(run(source), Ok("3\n5\n5\n".to_string()));
+    }
+
+    #[test]
+    fn test_uninitialized_var_is_nil() {
+        assert_eq!(run("var a; print a;"), Ok("nil\n".to_string()));
+    }
+
+    #[test]
+    fn test_interpret_values() {
+        let tokens = scan_lines("print 1; var a = \"x\"; print a;")
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect();
+        let stmts = parse_program(tokens).unwrap();
+
+        assert_eq!(
+            interpret_values(stmts, &mut Environment::new()),
+            Ok(vec![Value::Number(1.0), Value::Str("x".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_runtime_error_stops_execution() {
+        assert_eq!(
+            run("print 1; print -\"x\"; print 2;"),
+            Err(RuntimeError::new("Operand must be a number."))
+        );
+    }
+}
@@ -0,0 +1,109 @@
+pub mod ast;
+pub mod environment;
+pub mod format;
+pub mod interpreter;
+pub mod parser;
+mod parser_utils;
+pub mod stmt;
+pub mod tokens;
+pub mod value;
+
+use crate::environment::Environment;
+use crate::format::PrintOptions;
+use crate::interpreter::{interpret_to, interpret_values, write_value};
+use crate::parser::{ParseError, build_ast, parse_program};
+use crate::stmt::Stmt;
+use crate::tokens::{ScanError, scan_lines};
+use crate::value::{RuntimeError, Value};
+use std::fmt;
+use std::io::Write;
+
+/// Anything that can stop a Lox program, from whichever stage it came.
+#[derive(Debug, PartialEq)]
+pub enum LoxError {
+    Scan(ScanError),
+    Parse(ParseError),
+    Runtime(RuntimeError),
+}
+
+impl fmt::Display for LoxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoxError::Scan(error) => error.fmt(f),
+            LoxError::Parse(error) => error.fmt(f),
+            LoxError::Runtime(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for LoxError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoxError::Scan(error) => Some(error),
+            LoxError::Parse(error) => Some(error),
+            LoxError::Runtime(error) => Some(error),
+        }
+    }
+}
+
+impl From<ScanError> for LoxError {
+    fn from(error: ScanError) -> Self {
+        LoxError::Scan(error)
+    }
+}
+
+impl From<ParseError> for LoxError {
+    fn from(error: ParseError) -> Self {
+        LoxError::Parse(error)
+    }
+}
+
+impl From<RuntimeError> for LoxError {
+    fn from(error: RuntimeError) -> Self {
+        LoxError::Runtime(error)
+    }
+}
+
+/// Scan, parse and run `source` in a fresh environment, returning the values it printed.
+pub fn run(source: &str) -> Result<Vec<Value>, LoxError> {
+    Ok(interpret_values(compile(source)?, &mut Environment::new())?)
+}
+
+/// Scan, parse and run `source` against `env`, writing what it prints to `out` as it goes with
+/// numbers formatted by `options`.
+pub fn run_with(
+    source: &str,
+    env: &mut Environment,
+    out: &mut impl Write,
+    options: &PrintOptions,
+) -> Result<(), LoxError> {
+    Ok(interpret_to(compile(source)?, env, out, options)?)
+}
+
+/// `run_with` for a line typed at the prompt, which may also be a bare expression such as
+/// `1 + 2`. Its value is evaluated and written to `out` as if it were printed.
+pub fn run_line(
+    source: &str,
+    env: &mut Environment,
+    out: &mut impl Write,
+    options: &PrintOptions,
+) -> Result<(), LoxError> {
+    let tokens: Vec<_> = scan_lines(source)?.into_iter().flatten().collect();
+    let stmts = match parse_program(tokens.clone()) {
+        Ok(stmts) => stmts,
+        // Report the statement error if the line isn't an expression either
+        Err(err) => match build_ast(tokens) {
+            Ok(expr) => {
+                let value = expr.eval(env)?;
+                return Ok(write_value(out, &value, options)?);
+            }
+            Err(_) => return Err(err.into()),
+        },
+    };
+    Ok(interpret_to(stmts, env, out, options)?)
+}
+
+fn compile(source: &str) -> Result<Vec<Stmt>, LoxError> {
+    let tokens = scan_lines(source)?.into_iter().flatten().collect();
+    Ok(parse_program(tokens)?)
+}
@@ -1,67 +1,103 @@
-mod parser_utils;
-mod tokens;
-mod ast;
-mod parser;
+mod history;
 
-use crate::tokens::scan_line;
-use std::fmt::Debug;
+use crate::history::History;
+use lox::LoxError;
+use lox::environment::Environment;
+use lox::format::PrintOptions;
+use lox::tokens::{format_lines, scan_lines};
+use std::io::{BufRead, Write};
 
 struct Lox {
     // Define the structure of the Lox interpreter
     had_error: bool,
+    had_runtime_error: bool,
+    print_options: PrintOptions,
 }
 
 impl Lox {
     fn new() -> Self {
-        Self { had_error: false }
-    }
-
-    fn error(&mut self, line: u32, message: &str) {
-        self.report(line, "", message);
-    }
-
-    fn report(&mut self, line: u32, column: &str, message: &str) {
-        eprintln!("[line {}] Error at {}: {}", line, column, message);
-
-        self.had_error = true;
+        Self {
+            had_error: false,
+            had_runtime_error: false,
+            print_options: PrintOptions::default(),
+        }
     }
 
     fn run_file(&mut self, path: &String) {
         let contents = std::fs::read_to_string(path).expect("Could not read file");
-        Lox::run(&contents);
+        let result = lox::run_with(
+            &contents,
+            &mut Environment::new(),
+            &mut std::io::stdout(),
+            &self.print_options,
+        );
+        self.report_result(result);
         if self.had_error {
             std::process::exit(65);
         }
+        if self.had_runtime_error {
+            std::process::exit(70);
+        }
+    }
+
+    /// Print the tokens of a file grouped under their source line numbers.
+    fn emit_tokens_per_line(&mut self, path: &String) {
+        let contents = std::fs::read_to_string(path).expect("Could not read file");
+        match scan_lines(&contents) {
+            Ok(lines) => print!("{}", format_lines(&lines)),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(65);
+            }
+        }
     }
 
-    fn run_prompt(&mut self) {
+    /// Read and run lines from `reader` until `exit`, an empty line or EOF, recording each one
+    /// in `history` if given. With history enabled, `:history` lists the recorded lines; the
+    /// colon keeps it from shadowing a Lox variable called `history`.
+    /// Variables defined on one line stay visible on the next.
+    fn run_prompt(&mut self, reader: &mut impl BufRead, mut history: Option<History>) {
+        let mut env = Environment::new();
         loop {
             let mut input = String::new();
-            println!("> ");
-            std::io::stdin()
-                .read_line(&mut input)
-                .expect("Failed to read line");
+            print!("> ");
+            std::io::stdout().flush().expect("Failed to flush prompt");
+            reader.read_line(&mut input).expect("Failed to read line");
             if input.trim() == "exit" || input.trim() == "" {
                 break;
             }
-            Lox::run(&input);
+            if let Some(history) = history.as_mut() {
+                if input.trim() == ":history" {
+                    for entry in history.entries() {
+                        println!("{}", entry);
+                    }
+                    continue;
+                }
+                history.append(input.trim_end());
+            }
+            let result = lox::run_line(
+                &input,
+                &mut env,
+                &mut std::io::stdout(),
+                &self.print_options,
+            );
+            self.report_result(result);
 
             // Clear the error state after each prompt
-            if self.had_error {
-                self.had_error = false;
-            }
+            self.had_error = false;
+            self.had_runtime_error = false;
         }
     }
 
-    fn run(input: &String) {
-        // let mut scanner = Scanner::new(input.clone());
-        let tokens = scan_line(input);
-
-        for token in tokens {
-            // Print or process the token
-            println!("{:?}", token);
+    /// Report a failed run the way its stage calls for, flagging it for the exit code.
+    fn report_result(&mut self, result: Result<(), LoxError>) {
+        if let Err(err) = result {
+            eprintln!("{}", err);
+            match err {
+                LoxError::Runtime(_) => self.had_runtime_error = true,
+                LoxError::Scan(_) | LoxError::Parse(_) => self.had_error = true,
+            }
         }
-        println!("Bye!");
     }
 }
 
@@ -70,15 +106,39 @@ fn main() {
 
     let mut lox = Lox::new();
 
-    println!("{:?}", args);
-
-    if args.len() == 2 {
+    if args.len() == 3 && args[1] == "--emit-tokens-per-line" {
+        lox.emit_tokens_per_line(&args[2]);
+    } else if args.len() == 2 && args[1] == "--repl-history" {
+        let history = History::default_path().map(History::load);
+        lox.run_prompt(&mut std::io::stdin().lock(), history);
+    } else if args.len() == 2 {
         lox.run_file(&args[1]);
     } else if args.len() == 1 {
-        lox.run_prompt();
+        lox.run_prompt(&mut std::io::stdin().lock(), None);
     } else {
-        println!("Usage: rlox [path]");
+        println!("Usage: rlox [--emit-tokens-per-line path | --repl-history | path]");
         // system exit 64
         std::process::exit(64);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_records_history() {
+        let path = std::env::temp_dir().join(format!("rlox_prompt_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut lox = Lox::new();
+        let mut input = "var x = 1;\n:history\nprint x;\nexit\n".as_bytes();
+        lox.run_prompt(&mut input, Some(History::load(path.clone())));
+
+        // A new session picks up what the last one typed, without `:history` or `exit`
+        let history = History::load(path.clone());
+        assert_eq!(history.entries(), ["var x = 1;", "print x;"]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,12 +1,531 @@
-use crate::ast::{Binary, Expr, NumberLiteral};
-use crate::tokens::Token;
+use crate::ast::{
+    Assign, Binary, BooleanLiteral, Expr, Grouping, Logical, NilLiteral, NumberLiteral,
+    StringLiteral, Unary, Variable,
+};
+use crate::stmt::Stmt;
+use crate::tokens::{LocatedToken, Span, Token};
+use std::fmt;
 
+/// Why and where parsing stopped.
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub line: u32,
+    pub column: u32,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[line {}] Error at column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
 
 /// Create AST from tokens
-fn build_ast(tokens: Vec<Token>) -> Box<dyn Expr> {
-    Binary::new(
-        NumberLiteral::new(2.3),
-        Token::Plus,
-        NumberLiteral::new(1.2),
-    )
-}
\ No newline at end of file
+///
+/// A recursive-descent parser, one method per precedence level from loosest to tightest:
+///
+/// ```text
+/// expression → assignment
+/// assignment → IDENTIFIER "=" assignment | logic_or
+/// logic_or   → logic_and ( "or" logic_and )*
+/// logic_and  → equality ( "and" equality )*
+/// equality   → comparison ( ( "!=" | "==" ) comparison )*
+/// comparison → term ( ( ">" | ">=" | "<" | "<=" ) term )*
+/// term       → factor ( ( "-" | "+" ) factor )*
+/// factor     → unary ( ( "/" | "*" ) unary )*
+/// unary      → ( "!" | "-" ) unary | primary
+/// primary    → NUMBER | STRING | IDENTIFIER | "true" | "false" | "nil" | "(" expression ")"
+/// ```
+pub fn build_ast(tokens: Vec<LocatedToken>) -> Result<Box<dyn Expr>, ParseError> {
+    let mut parser = Parser::new(tokens);
+    let expr = parser.expression()?;
+
+    if !parser.is_at_end() {
+        return Err(parser.error("Expect end of expression."));
+    }
+
+    Ok(expr)
+}
+
+/// Parse a whole program of statements:
+///
+/// ```text
+/// program     → declaration* EOF
+/// declaration → varDecl | statement
+/// varDecl     → "var" IDENTIFIER ( "=" expression )? ";"
+/// statement   → printStmt | exprStmt
+/// printStmt   → "print" expression ";"
+/// exprStmt    → expression ";"
+/// ```
+pub fn parse_program(tokens: Vec<LocatedToken>) -> Result<Vec<Stmt>, ParseError> {
+    let mut parser = Parser::new(tokens);
+    let mut stmts = Vec::new();
+
+    while !parser.is_at_end() {
+        stmts.push(parser.declaration()?);
+    }
+
+    Ok(stmts)
+}
+
+struct Parser {
+    tokens: Vec<LocatedToken>,
+    current: usize,
+}
+
+impl Parser {
+    fn new(tokens: Vec<LocatedToken>) -> Self {
+        // Comments carry no meaning for the parser
+        let tokens = tokens
+            .into_iter()
+            .filter(|located| {
+                !matches!(
+                    located.token,
+                    Token::LineComment(_) | Token::BlockComment(_)
+                )
+            })
+            .collect();
+
+        Parser { tokens, current: 0 }
+    }
+
+    fn declaration(&mut self) -> Result<Stmt, ParseError> {
+        if self.match_any(&[Token::Var]).is_some() {
+            return self.var_declaration();
+        }
+
+        self.statement()
+    }
+
+    fn var_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = match self.peek() {
+            Some(Token::Identifier(name)) => Token::Identifier(name.clone()),
+            _ => return Err(self.error("Expect variable name.")),
+        };
+        self.advance();
+
+        let initializer = match self.match_any(&[Token::Equal]) {
+            Some(_) => Some(self.expression()?),
+            None => None,
+        };
+        self.consume(Token::Semicolon, "Expect ';' after variable declaration.")?;
+
+        Ok(Stmt::Var(name, initializer))
+    }
+
+    fn statement(&mut self) -> Result<Stmt, ParseError> {
+        if self.match_any(&[Token::Print]).is_some() {
+            let value = self.expression()?;
+            self.consume(Token::Semicolon, "Expect ';' after value.")?;
+            return Ok(Stmt::Print(value));
+        }
+
+        let expr = self.expression()?;
+        self.consume(Token::Semicolon, "Expect ';' after expression.")?;
+        Ok(Stmt::Expression(expr))
+    }
+
+    fn expression(&mut self) -> Result<Box<dyn Expr>, ParseError> {
+        self.assignment()
+    }
+
+    fn assignment(&mut self) -> Result<Box<dyn Expr>, ParseError> {
+        if let (Some(Token::Identifier(_)), Some(Token::Equal)) = (self.peek(), self.peek_next()) {
+            let name = self.peek().cloned().unwrap();
+            self.advance();
+            self.advance();
+            let value = self.assignment()?;
+            return Ok(Assign::new(name, value));
+        }
+
+        let expr = self.logic_or()?;
+        if self.peek() == Some(&Token::Equal) {
+            return Err(self.error("Invalid assignment target."));
+        }
+
+        Ok(expr)
+    }
+
+    fn logic_or(&mut self) -> Result<Box<dyn Expr>, ParseError> {
+        let mut expr = self.logic_and()?;
+
+        while let Some(operator) = self.match_any(&[Token::Or]) {
+            let right = self.logic_and()?;
+            expr = Logical::new(expr, operator, right);
+        }
+
+        Ok(expr)
+    }
+
+    fn logic_and(&mut self) -> Result<Box<dyn Expr>, ParseError> {
+        let mut expr = self.equality()?;
+
+        while let Some(operator) = self.match_any(&[Token::And]) {
+            let right = self.equality()?;
+            expr = Logical::new(expr, operator, right);
+        }
+
+        Ok(expr)
+    }
+
+    fn equality(&mut self) -> Result<Box<dyn Expr>, ParseError> {
+        let mut expr = self.comparison()?;
+
+        while let Some(operator) = self.match_any(&[Token::BangEqual, Token::EqualEqual]) {
+            let right = self.comparison()?;
+            expr = Logical::new(expr, operator, right);
+        }
+
+        Ok(expr)
+    }
+
+    fn comparison(&mut self) -> Result<Box<dyn Expr>, ParseError> {
+        let mut expr = self.term()?;
+
+        while let Some(operator) = self.match_any(&[
+            Token::Greater,
+            Token::GreaterEqual,
+            Token::Less,
+            Token::LessEqual,
+        ]) {
+            let right = self.term()?;
+            expr = Logical::new(expr, operator, right);
+        }
+
+        Ok(expr)
+    }
+
+    fn term(&mut self) -> Result<Box<dyn Expr>, ParseError> {
+        let start = self.current;
+        let mut expr = self.factor()?;
+
+        while let Some(operator) = self.match_any(&[Token::Minus, Token::Plus]) {
+            let right = self.factor()?;
+            expr = Binary::new(expr, operator, right).with_span(self.span_from(start));
+        }
+
+        Ok(expr)
+    }
+
+    fn factor(&mut self) -> Result<Box<dyn Expr>, ParseError> {
+        let start = self.current;
+        let mut expr = self.unary()?;
+
+        while let Some(operator) = self.match_any(&[Token::Slash, Token::Star]) {
+            let right = self.unary()?;
+            expr = Binary::new(expr, operator, right).with_span(self.span_from(start));
+        }
+
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Box<dyn Expr>, ParseError> {
+        if let Some(operator) = self.match_any(&[Token::Bang, Token::Minus]) {
+            let right = self.unary()?;
+            return Ok(Unary::new(operator, right));
+        }
+
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Box<dyn Expr>, ParseError> {
+        let expr: Box<dyn Expr> = match self.peek() {
+            Some(Token::False) => BooleanLiteral::new(false),
+            Some(Token::True) => BooleanLiteral::new(true),
+            Some(Token::Nil) => NilLiteral::new(),
+            Some(Token::Number(value)) => NumberLiteral::new(*value),
+            Some(Token::String(value)) => StringLiteral::new(value.clone()),
+            Some(Token::Identifier(name)) => Variable::new(Token::Identifier(name.clone())),
+            Some(Token::LeftParen) => {
+                self.advance();
+                let expr = self.expression()?;
+                if self.peek() != Some(&Token::RightParen) {
+                    return Err(self.error("Expect ')' after expression."));
+                }
+                Grouping::new(expr)
+            }
+            _ => return Err(self.error("Expect expression.")),
+        };
+        self.advance();
+
+        Ok(expr)
+    }
+
+    /// Consume the next token, which must be `kind`.
+    fn consume(&mut self, kind: Token, message: &str) -> Result<(), ParseError> {
+        if self.peek() != Some(&kind) {
+            return Err(self.error(message));
+        }
+        self.advance();
+        Ok(())
+    }
+
+    /// Consume and return the next token if it's one of `kinds`.
+    fn match_any(&mut self, kinds: &[Token]) -> Option<Token> {
+        let token = self.peek()?;
+        if kinds.contains(token) {
+            let token = token.clone();
+            self.advance();
+            Some(token)
+        } else {
+            None
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.current).map(|located| &located.token)
+    }
+
+    fn peek_next(&self) -> Option<&Token> {
+        self.tokens
+            .get(self.current + 1)
+            .map(|located| &located.token)
+    }
+
+    /// Whether every token up to `Token::Eof` (or the end, for token lists without one) is used.
+    fn is_at_end(&self) -> bool {
+        matches!(self.peek(), None | Some(Token::Eof))
+    }
+
+    fn advance(&mut self) {
+        if !self.is_at_end() {
+            self.current += 1;
+        }
+    }
+
+    /// The span from the token at `start` through the last one consumed.
+    fn span_from(&self, start: usize) -> Span {
+        self.tokens[start]
+            .span()
+            .merge(&self.tokens[self.current - 1].span())
+    }
+
+    /// An error at the current token, or just past the last one at the end of input.
+    fn error(&self, message: &str) -> ParseError {
+        let (line, column) = match self.tokens.get(self.current) {
+            Some(located) => (located.line, located.column),
+            None => self
+                .tokens
+                .last()
+                .map_or((1, 1), |located| (located.line, located.column + 1)),
+        };
+
+        ParseError {
+            line,
+            column,
+            message: message.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{AstPrinter, Style};
+    use crate::environment::Environment;
+    use crate::tokens::scan_lines;
+    use crate::value::{RuntimeError, Value};
+
+    fn parse(source: &str) -> Result<Box<dyn Expr>, ParseError> {
+        let tokens = scan_lines(source).unwrap().into_iter().flatten().collect();
+        build_ast(tokens)
+    }
+
+    fn print(source: &str) -> String {
+        AstPrinter::new(Style::Lisp).print(parse(source).unwrap().as_ref())
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(print("1 + 2 * 3"), "(+ 1 (* 2 3))");
+        assert_eq!(print("1 * 2 + 3"), "(+ (* 1 2) 3)");
+        assert_eq!(print("1 < 2 == 3 > 4"), "(== (< 1 2) (> 3 4))");
+        assert_eq!(print("a or b and c == d"), "(or a (and b (== c d)))");
+    }
+
+    #[test]
+    fn test_binary_span() {
+        let expr = parse("1 + 2 * 3").unwrap();
+        assert_eq!(
+            expr.span(),
+            Some(Span {
+                start: (1, 1),
+                end: (1, 10)
+            })
+        );
+    }
+
+    #[test]
+    fn test_left_associative() {
+        assert_eq!(print("1 - 2 - 3"), "(- (- 1 2) 3)");
+    }
+
+    #[test]
+    fn test_unary_over_grouping() {
+        assert_eq!(print("-(1 + 2)"), "(- (group (+ 1 2)))");
+        assert_eq!(print("!!true"), "(! (! true))");
+    }
+
+    #[test]
+    fn test_negative_literal_is_unary_minus() {
+        // The scanner never produces negative numbers, so `-5` is a negation of `5`
+        assert_eq!(print("-5 + 3"), "(+ (- 5) 3)");
+        assert_eq!(
+            parse("-5 + 3").unwrap().eval(&mut Environment::new()),
+            Ok(Value::Number(-2.0))
+        );
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(print("\"hi\" == nil"), "(== hi nil)");
+    }
+
+    #[test]
+    fn test_variables_and_assignment() {
+        assert_eq!(print("x + 1"), "(+ x 1)");
+        assert_eq!(print("a = b = 2"), "(= a (= b 2))");
+
+        let err = parse("1 = 2").err().unwrap();
+        assert_eq!(err.message, "Invalid assignment target.");
+        assert_eq!(err.column, 3);
+    }
+
+    #[test]
+    fn test_eval_parsed_variable() {
+        let mut env = Environment::new();
+        env.define("x", Value::Number(5.0)).unwrap();
+
+        assert_eq!(
+            parse("x + 1").unwrap().eval(&mut env),
+            Ok(Value::Number(6.0))
+        );
+        assert_eq!(
+            parse("y").unwrap().eval(&mut env),
+            Err(RuntimeError::new("Undefined variable 'y'."))
+        );
+    }
+
+    #[test]
+    fn test_eval_parsed_and_or() {
+        let eval = |source| parse(source).unwrap().eval(&mut Environment::new());
+
+        // Each yields the operand that decided it rather than a bool
+        assert_eq!(eval("nil or \"x\""), Ok(Value::Str("x".to_string())));
+        assert_eq!(eval("\"a\" and \"b\""), Ok(Value::Str("b".to_string())));
+        assert_eq!(eval("false and 1"), Ok(Value::Bool(false)));
+        assert_eq!(eval("1 or 2"), Ok(Value::Number(1.0)));
+    }
+
+    #[test]
+    fn test_parse_program() {
+        let tokens = scan_lines("var x = 1;\nvar y;\nprint x + 1;\nx = 2;")
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect();
+        let stmts = parse_program(tokens).unwrap();
+
+        let printer = AstPrinter::new(Style::Lisp);
+        let printed: Vec<String> = stmts
+            .iter()
+            .map(|stmt| match stmt {
+                Stmt::Print(expr) => format!("print {}", printer.print(expr.as_ref())),
+                Stmt::Expression(expr) => printer.print(expr.as_ref()),
+                Stmt::Var(name, None) => format!("var {:?}", name),
+                Stmt::Var(name, Some(expr)) => {
+                    format!("var {:?} {}", name, printer.print(expr.as_ref()))
+                }
+            })
+            .collect();
+        assert_eq!(
+            printed,
+            vec![
+                "var Identifier(\"x\") 1",
+                "var Identifier(\"y\")",
+                "print (+ x 1)",
+                "(= x 2)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_semicolon() {
+        let tokens = scan_lines("print 1\nprint 2;")
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect();
+        let err = parse_program(tokens).err().unwrap();
+        assert_eq!(
+            err,
+            ParseError {
+                line: 2,
+                column: 1,
+                message: "Expect ';' after value.".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_print_without_value() {
+        let tokens = scan_lines("print;")
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect();
+        let err = parse_program(tokens).err().unwrap();
+        assert_eq!(
+            err,
+            ParseError {
+                line: 1,
+                column: 6,
+                message: "Expect expression.".to_string(),
+            }
+        );
+
+        let tokens = scan_lines("print nil;")
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect();
+        let stmts = parse_program(tokens).unwrap();
+        assert!(matches!(stmts.as_slice(), [Stmt::Print(expr)] if expr.print_lisp() == "nil"));
+    }
+
+    #[test]
+    fn test_unclosed_group() {
+        let err = parse("(1 + 2").err().unwrap();
+        assert_eq!(
+            err,
+            ParseError {
+                line: 1,
+                column: 7,
+                message: "Expect ')' after expression.".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_missing_operand() {
+        let err = parse("1 + * 2").err().unwrap();
+        assert_eq!(err.message, "Expect expression.");
+        assert_eq!(err.column, 5);
+
+        let err = parse("").err().unwrap();
+        assert_eq!(err.message, "Expect expression.");
+    }
+
+    #[test]
+    fn test_trailing_tokens() {
+        let err = parse("1 2").err().unwrap();
+        assert_eq!(err.message, "Expect end of expression.");
+        assert_eq!(err.column, 3);
+    }
+}
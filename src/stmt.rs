@@ -0,0 +1,11 @@
+use crate::ast::Expr;
+use crate::tokens::Token;
+
+pub enum Stmt {
+    /// `print expr;`
+    Print(Box<dyn Expr>),
+    /// An expression evaluated for its side effects, e.g. an assignment.
+    Expression(Box<dyn Expr>),
+    /// `var name;` or `var name = expr;`, where the token is the `Token::Identifier` name.
+    Var(Token, Option<Box<dyn Expr>>),
+}
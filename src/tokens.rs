@@ -1,21 +1,92 @@
 use crate::parser_utils::ws;
 use crate::ws_separated;
 use nom::branch::alt;
-use nom::bytes::complete::{is_not, tag};
+use nom::bytes::complete::tag;
 use nom::character::complete::{
-    alpha1, alphanumeric0, alphanumeric1, line_ending, not_line_ending,
+    alpha1, alphanumeric0, alphanumeric1, digit1, line_ending, multispace0, not_line_ending,
 };
+use nom::combinator::{consumed, map_res, opt, recognize};
 use nom::multi::many0;
-use nom::number::complete::double;
-use nom::sequence::delimited;
-use nom::{IResult, Parser};
+use nom::sequence::{delimited, preceded, terminated};
+use nom::{IResult, Offset, Parser};
+use std::fmt;
 
-type Line = Vec<Token>;
+type Line = Vec<LocatedToken>;
 
+/// A scanned value along with the 1-based line and column (in characters) it started at, and
+/// the position just past its last character.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Located<T> {
+    pub token: T,
+    pub line: u32,
+    pub column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+}
+
+impl<T> Located<T> {
+    /// The stretch of source this value was scanned from.
+    pub fn span(&self) -> Span {
+        Span {
+            start: (self.line, self.column),
+            end: (self.end_line, self.end_column),
+        }
+    }
+}
+
+/// A stretch of source from `start` up to, but not including, `end`. Both are 1-based
+/// `(line, column)` pairs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub start: (u32, u32),
+    pub end: (u32, u32),
+}
+
+impl Span {
+    /// The smallest span covering both `self` and `other`.
+    pub fn merge(&self, other: &Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+pub type LocatedToken = Located<Token>;
+
+/// Why and where scanning stopped.
 #[derive(Debug, PartialEq)]
+pub struct ScanError {
+    pub line: u32,
+    pub column: u32,
+    pub message: String,
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[line {}] Error at column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for ScanError {}
+
+/// Limits applied while scanning, for scanning untrusted input.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ScannerOptions {
+    /// Longest string literal accepted, in bytes. `None` (the default) means unlimited.
+    ///
+    /// A longer literal is rejected with "String literal too long." at its opening quote.
+    pub max_string_len: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     // Single character tokens
-    I,
+    LeftParen,
     RightParen,
     LeftBrace,
     RightBrace,
@@ -61,44 +132,265 @@ pub enum Token {
     While,
 
     LineComment(String),
+    BlockComment(String),
+
+    // Trivia, only produced by `tokenize_with_trivia`
+    Whitespace(String),
+    Newline,
+    Shebang(String),
+
     Eof,
 }
 
-pub fn scan_lines(input: &str) -> Result<Vec<Line>, nom::Err<nom::error::Error<&str>>> {
-    let mut lines: Vec<Line> = Vec::new();
-    for line in input.lines() {
-        let result = scan_line(line);
+/// Scan every line of `input`, returning one `Line` of tokens per source line.
+///
+/// The source is scanned as a whole, so a string literal may span several lines; each token is
+/// grouped under the line it starts on. A single `Token::Eof` at the end of the source ends the
+/// last line. A shebang (`#!...`) on the very first line is skipped and
+/// yields an empty `Line`, so the index of each line still matches its position in the source.
+pub fn scan_lines(input: &str) -> Result<Vec<Line>, ScanError> {
+    scan_lines_with_options(input, &ScannerOptions::default())
+}
 
-        // If result is not OK return error
-        if result.is_err() {
-            return Err(result.err().unwrap());
-        }
+/// `scan_lines` with the limits in `options` applied.
+pub fn scan_lines_with_options(
+    input: &str,
+    options: &ScannerOptions,
+) -> Result<Vec<Line>, ScanError> {
+    let start = if input.starts_with("#!") {
+        input.find('\n').unwrap_or(input.len())
+    } else {
+        0
+    };
 
-        let (remaining, tokens) = result?;
+    let error_at = |rest: &str, message: &str| {
+        let (line, column) = Positions::new(input).locate(rest);
+        ScanError {
+            line,
+            column,
+            message: message.to_string(),
+        }
+    };
 
-        if !remaining.is_empty() {
-            return Err(nom::Err::Error(nom::error::Error::new(
-                remaining,
-                nom::error::ErrorKind::NonEmpty,
-            )));
+    let (remaining, mut tokens) = match scan_from(input, &input[start..], options) {
+        Ok(result) => result,
+        Err(nom::Err::Error(err) | nom::Err::Failure(err)) => {
+            let message = match err.code {
+                nom::error::ErrorKind::TooLarge => "String literal too long.",
+                nom::error::ErrorKind::Eof => "Unterminated string.",
+                nom::error::ErrorKind::TakeUntil => "Unterminated block comment.",
+                nom::error::ErrorKind::Escaped => "Invalid escape sequence.",
+                _ => "Unexpected character.",
+            };
+            return Err(error_at(err.input, message));
         }
-        lines.push(tokens);
+        Err(nom::Err::Incomplete(_)) => unreachable!("complete parsers never need more input"),
+    };
+
+    if !remaining.is_empty() {
+        return Err(error_at(remaining, "Unexpected character."));
+    }
+
+    let (line, column) = Positions::new(input).locate(&input[input.len()..]);
+    tokens.push(Located {
+        token: Token::Eof,
+        line,
+        column,
+        end_line: line,
+        end_column: column,
+    });
+
+    let line_count = tokens
+        .last()
+        .map_or(0, |located| located.line as usize)
+        .max(input.lines().count());
+    let mut lines: Vec<Line> = (0..line_count).map(|_| Vec::new()).collect();
+    for located in tokens {
+        lines[located.line as usize - 1].push(located);
     }
     Ok(lines)
 }
 
+/// Converts positions in `source` into 1-based line and character column numbers. Positions must
+/// be located in increasing order, so a whole scan costs a single pass over the source.
+struct Positions<'a> {
+    source: &'a str,
+    offset: usize,
+    line: u32,
+    column: u32,
+}
+
+impl<'a> Positions<'a> {
+    fn new(source: &'a str) -> Self {
+        Positions {
+            source,
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// The line and column at which `rest`, a suffix of the source, starts.
+    fn locate(&mut self, rest: &str) -> (u32, u32) {
+        let target = self.source.offset(rest);
+        for c in self.source[self.offset..target].chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+        self.offset = target;
+
+        (self.line, self.column)
+    }
+}
+
+/// Format scanned lines for debugging, one `line N: ...` row per line that has tokens.
+pub fn format_lines(lines: &[Line]) -> String {
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, tokens)| !tokens.is_empty())
+        .map(|(index, tokens)| {
+            let tokens: Vec<String> = tokens
+                .iter()
+                .map(|located| format!("{:?}", located.token))
+                .collect();
+            format!("line {}: {}\n", index + 1, tokens.join(" "))
+        })
+        .collect()
+}
+
 /// Use nom to parse lines of lox code and return a vector of tokens.
-pub fn scan_line(input: &str) -> IResult<&str, Vec<Token>> {
-    many0(alt(ws_separated!((
-        line_comment,
+///
+/// Unlike `scan_lines` no `Token::Eof` is added, as `input` may only be a fragment of a source.
+pub fn scan_line(input: &str) -> IResult<&str, Vec<LocatedToken>> {
+    scan_line_with_options(input, &ScannerOptions::default())
+}
+
+/// `scan_line` with the limits in `options` applied.
+pub fn scan_line_with_options<'a>(
+    input: &'a str,
+    options: &ScannerOptions,
+) -> IResult<&'a str, Vec<LocatedToken>> {
+    scan_from(input, input, options)
+}
+
+/// Scan tokens from `rest`, a suffix of `source`, locating them relative to the start of `source`.
+fn scan_from<'a>(
+    source: &str,
+    rest: &'a str,
+    options: &ScannerOptions,
+) -> IResult<&'a str, Vec<LocatedToken>> {
+    let max_string_len = options.max_string_len;
+
+    // `ws` only skips whitespace around a token, so whitespace with no token after it (such as
+    // a source that is only a newline) is consumed at the end
+    terminated(
+        many0(consumed(alt(ws_separated!((
+            line_comment,
+            block_comment,
+            keyword,
+            identifier,
+            number,
+            |input| limited_string(input, max_string_len),
+            // Before single_char_token so `<=` isn't split into `<` and `=`
+            two_char_token,
+            single_char_token
+        ))))),
+        multispace0,
+    )
+    .map(|tokens| {
+        let mut positions = Positions::new(source);
+        tokens
+            .into_iter()
+            .map(|(lexeme, token)| {
+                // `lexeme` includes the whitespace `ws` skipped around the token
+                let start = lexeme.len() - lexeme.trim_start().len();
+                let (line, column) = positions.locate(&lexeme[start..]);
+                let (end_line, end_column) = positions.locate(&lexeme[lexeme.trim_end().len()..]);
+                Located {
+                    token,
+                    line,
+                    column,
+                    end_line,
+                    end_column,
+                }
+            })
+            .collect()
+    })
+    .parse(rest)
+}
+
+/// Scan `input` without discarding whitespace, newlines or comment line endings.
+///
+/// Each token is paired with the exact slice of source it was scanned from, so concatenating
+/// the slices reproduces the input byte-for-byte. Intended for tools such as formatters that
+/// need a lossless token stream. A first-line shebang, which `scan_lines` skips, comes back as
+/// a `Token::Shebang`.
+pub fn tokenize_with_trivia(input: &str) -> IResult<&str, Vec<(Token, &str)>> {
+    let (rest, shebang) = opt(consumed(shebang)).parse(input)?;
+
+    let (remaining, tokens) = many0(consumed(alt((
+        whitespace,
+        newline,
+        trivia_line_comment,
+        block_comment,
         keyword,
         identifier,
         number,
         string,
         two_char_token,
-        single_char_token
+        single_char_token,
     ))))
-    .parse(input)
+    .parse(rest)?;
+
+    let tokens = shebang
+        .into_iter()
+        .chain(tokens)
+        .map(|(lexeme, token)| (token, lexeme))
+        .collect();
+    Ok((remaining, tokens))
+}
+
+fn shebang(input: &str) -> IResult<&str, Token> {
+    let (remaining, line) = preceded(tag("#!"), not_line_ending).parse(input)?;
+    Ok((remaining, Token::Shebang(line.to_string())))
+}
+
+/// Spaces, tabs and any `\r` that doesn't start a `\r\n` line ending, matching what the
+/// scanner skips between tokens.
+fn whitespace(input: &str) -> IResult<&str, Token> {
+    let len = input
+        .char_indices()
+        .find(|&(index, c)| match c {
+            ' ' | '\t' => false,
+            '\r' => input[index + 1..].starts_with('\n'),
+            _ => true,
+        })
+        .map_or(input.len(), |(index, _)| index);
+    if len == 0 {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Space,
+        )));
+    }
+
+    let (lexeme, remaining) = input.split_at(len);
+    Ok((remaining, Token::Whitespace(lexeme.to_string())))
+}
+
+fn newline(input: &str) -> IResult<&str, Token> {
+    let (remaining, _) = line_ending(input)?;
+    Ok((remaining, Token::Newline))
+}
+
+/// Like `line_comment`, but leaves the line ending to be scanned as a `Token::Newline`.
+fn trivia_line_comment(input: &str) -> IResult<&str, Token> {
+    let (remaining, comment) = preceded(tag("//"), not_line_ending).parse(input)?;
+    Ok((remaining, Token::LineComment(comment.to_string())))
 }
 
 fn line_comment(input: &str) -> IResult<&str, Token> {
@@ -107,6 +399,36 @@ fn line_comment(input: &str) -> IResult<&str, Token> {
     Ok((remaining, Token::LineComment(comment.to_string())))
 }
 
+/// A `/* ... */` comment, which may contain nested block comments. Fails with
+/// `ErrorKind::TakeUntil` at the opening `/*` if the comment is never closed.
+fn block_comment(input: &str) -> IResult<&str, Token> {
+    let (body, _) = tag("/*").parse(input)?;
+
+    let mut depth = 1;
+    let mut rest = body;
+    while depth > 0 {
+        if let Some(after) = rest.strip_prefix("/*") {
+            depth += 1;
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("*/") {
+            depth -= 1;
+            rest = after;
+        } else if let Some(next) = rest.chars().next() {
+            rest = &rest[next.len_utf8()..];
+        } else {
+            // Fail rather than error so the `/*` isn't rescanned as `/` and `*`
+            return Err(nom::Err::Failure(nom::error::Error::new(
+                input,
+                nom::error::ErrorKind::TakeUntil,
+            )));
+        }
+    }
+
+    // Everything between the outer delimiters
+    let comment = &body[..body.offset(rest) - 2];
+    Ok((rest, Token::BlockComment(comment.to_string())))
+}
+
 fn single_char_token(input: &str) -> IResult<&str, Token> {
     let (remaining, lexeme) = alt((
         tag("("),
@@ -121,11 +443,14 @@ fn single_char_token(input: &str) -> IResult<&str, Token> {
         tag("/"),
         tag("*"),
         tag("="),
+        tag("!"),
+        tag("<"),
+        tag(">"),
     ))
     .parse(input)?;
 
     let token_type = match lexeme {
-        "(" => Token::I,
+        "(" => Token::LeftParen,
         ")" => Token::RightParen,
         "{" => Token::LeftBrace,
         "}" => Token::RightBrace,
@@ -137,6 +462,9 @@ fn single_char_token(input: &str) -> IResult<&str, Token> {
         "/" => Token::Slash,
         "*" => Token::Star,
         "=" => Token::Equal,
+        "!" => Token::Bang,
+        "<" => Token::Less,
+        ">" => Token::Greater,
         _ => unreachable!(),
     };
 
@@ -165,16 +493,83 @@ fn identifier(input: &str) -> IResult<&str, Token> {
 }
 
 fn string(input: &str) -> IResult<&str, Token> {
-    let (remaining, lexeme) = delimited(tag("\""), is_not("\""), tag("\"")).parse(input)?;
-    Ok((remaining, Token::String(lexeme.to_string())))
+    limited_string(input, None)
 }
 
+/// A string literal no longer than `max_len` bytes once decoded, if given. The length is checked
+/// as the literal is decoded, so an oversized literal is never fully allocated.
+///
+/// The literal may span several lines. The escapes `\n`, `\t`, `\r`, `\\` and `\"` are
+/// decoded; any other escape fails with `ErrorKind::Escaped` at its backslash, and a literal
+/// with no closing quote fails with `ErrorKind::Eof` at its opening quote.
+fn limited_string(input: &str, max_len: Option<usize>) -> IResult<&str, Token> {
+    // Fail rather than error from here on so no other token parser is tried on the literal
+    let failure = |at, kind| Err(nom::Err::Failure(nom::error::Error::new(at, kind)));
+
+    let (mut rest, _) = tag("\"").parse(input)?;
+    let mut value = String::new();
+
+    loop {
+        let mut chars = rest.chars();
+        match chars.next() {
+            Some('"') => {
+                rest = chars.as_str();
+                break;
+            }
+            Some('\\') => {
+                let decoded = match chars.next() {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some('\\') => '\\',
+                    Some('"') => '"',
+                    Some(_) => return failure(rest, nom::error::ErrorKind::Escaped),
+                    None => return failure(input, nom::error::ErrorKind::Eof),
+                };
+                value.push(decoded);
+            }
+            Some(c) => value.push(c),
+            None => return failure(input, nom::error::ErrorKind::Eof),
+        }
+        rest = chars.as_str();
+
+        if max_len.is_some_and(|max_len| value.len() > max_len) {
+            return failure(input, nom::error::ErrorKind::TooLarge);
+        }
+    }
+
+    Ok((rest, Token::String(value)))
+}
+
+/// A number literal. Never negative: the `-` in `-5` is scanned as its own `Token::Minus` and
+/// applied by the parser's unary rule, so `a-5` is a subtraction rather than `a` followed by `-5`.
 fn number(input: &str) -> IResult<&str, Token> {
-    let (remaining, number) = double.parse(input)?;
+    let (remaining, number) = number_literal(input)?;
 
     Ok((remaining, Token::Number(number)))
 }
 
+/// The Lox numeric grammar: digits, optionally followed by `.` and more digits.
+///
+/// There is no sign, exponent, or leading/trailing `.`, so `.5` and `5.` do not scan as single
+/// numbers. Shared by the scanner and `parse_number` so the two cannot drift apart.
+fn number_literal(input: &str) -> IResult<&str, f64> {
+    map_res(
+        recognize((digit1, opt((tag("."), digit1)))),
+        str::parse::<f64>,
+    )
+    .parse(input)
+}
+
+/// Parse the whole of `input` as a Lox number literal, returning `None` if any of it is left
+/// over or it doesn't match the grammar the scanner accepts.
+pub fn parse_number(input: &str) -> Option<f64> {
+    match number_literal(input) {
+        Ok(("", number)) => Some(number),
+        _ => None,
+    }
+}
+
 /// Return a token from the input string which is a Lox keyword
 fn keyword(input: &str) -> IResult<&str, Token> {
     let (remaining, lexeme) = alphanumeric1(input)?;
@@ -205,10 +600,44 @@ fn keyword(input: &str) -> IResult<&str, Token> {
     Ok((remaining, token_type))
 }
 
+/// Wrappers around the individual token parsers, for downstream crates building and testing
+/// grammar extensions on top of the scanner. Only available with the `testing` feature, as these
+/// aren't part of the stable API.
+#[cfg(feature = "testing")]
+pub mod internal {
+    use super::Token;
+    use nom::IResult;
+
+    pub fn keyword(input: &str) -> IResult<&str, Token> {
+        super::keyword(input)
+    }
+
+    pub fn identifier(input: &str) -> IResult<&str, Token> {
+        super::identifier(input)
+    }
+
+    pub fn number(input: &str) -> IResult<&str, Token> {
+        super::number(input)
+    }
+
+    pub fn string(input: &str) -> IResult<&str, Token> {
+        super::string(input)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Scan a single line, dropping token positions.
+    fn scan_tokens(input: &str) -> (&str, Vec<Token>) {
+        let (remaining, tokens) = scan_line(input).unwrap();
+        (
+            remaining,
+            tokens.into_iter().map(|located| located.token).collect(),
+        )
+    }
+
     #[test]
     fn test_keyword() {
         let input = "and";
@@ -237,6 +666,95 @@ mod tests {
         // assert_eq!(token.lexeme, "123.45");
     }
 
+    #[test]
+    fn test_scan_parens() {
+        let (remaining, tokens) = scan_tokens("(1 + 2)");
+        assert_eq!(remaining, "");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::LeftParen,
+                Token::Number(1.0),
+                Token::Plus,
+                Token::Number(2.0),
+                Token::RightParen
+            ]
+        );
+    }
+
+    #[test]
+    fn test_single_char_comparisons() {
+        let (remaining, tokens) = scan_tokens("!done a < b > c");
+        assert_eq!(remaining, "");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Bang,
+                Token::Identifier("done".to_string()),
+                Token::Identifier("a".to_string()),
+                Token::Less,
+                Token::Identifier("b".to_string()),
+                Token::Greater,
+                Token::Identifier("c".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_two_char_tokens_are_not_split() {
+        let (remaining, tokens) = scan_tokens("a <= b != c");
+        assert_eq!(remaining, "");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Identifier("a".to_string()),
+                Token::LessEqual,
+                Token::Identifier("b".to_string()),
+                Token::BangEqual,
+                Token::Identifier("c".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_negative_number_is_minus_then_number() {
+        let tokens: Vec<Token> = scan_lines("-5")
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .map(|located| located.token)
+            .collect();
+        assert_eq!(tokens, vec![Token::Minus, Token::Number(5.0), Token::Eof]);
+
+        let (_, tokens) = scan_tokens("a-5");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Identifier("a".to_string()),
+                Token::Minus,
+                Token::Number(5.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_number_agrees_with_scanner() {
+        let (_, tokens) = scan_tokens("5.0");
+        assert_eq!(tokens, vec![Token::Number(5.0)]);
+        assert_eq!(parse_number("5.0"), Some(5.0));
+
+        // No exponents in strict Lox: the scanner stops at `e`
+        let (_, tokens) = scan_tokens("1e3");
+        assert_eq!(
+            tokens,
+            vec![Token::Number(1.0), Token::Identifier("e3".to_string())]
+        );
+        assert_eq!(parse_number("1e3"), None);
+
+        assert!(number("abc").is_err());
+        assert_eq!(parse_number("abc"), None);
+    }
+
     #[test]
     fn test_identifier() {
         let input = "myVariable123";
@@ -261,6 +779,115 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_string_escapes() {
+        let (remaining, tokens) = scan_tokens(r#""a\"b\n""#);
+        assert_eq!(remaining, "");
+        assert_eq!(tokens, vec![Token::String("a\"b\n".to_string())]);
+
+        let (_, tokens) = scan_tokens(r#""\t\r\\" """#);
+        assert_eq!(
+            tokens,
+            vec![
+                Token::String("\t\r\\".to_string()),
+                Token::String("".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_escape() {
+        let err = scan_lines(r#"print "ok\q";"#).unwrap_err();
+        assert_eq!(
+            err,
+            ScanError {
+                line: 1,
+                column: 10,
+                message: "Invalid escape sequence.".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_multi_line_string() {
+        let lines = scan_lines("var s = \"foo\nbar\";\nprint s;").unwrap();
+        let tokens: Vec<LocatedToken> = lines.into_iter().flatten().collect();
+
+        assert_eq!(tokens[3].token, Token::String("foo\nbar".to_string()));
+        assert_eq!((tokens[3].line, tokens[3].column), (1, 9));
+
+        // The semicolon closing the statement is on the string's second line
+        assert_eq!(tokens[4].token, Token::Semicolon);
+        assert_eq!((tokens[4].line, tokens[4].column), (2, 5));
+        assert_eq!(tokens[5].token, Token::Print);
+        assert_eq!((tokens[5].line, tokens[5].column), (3, 1));
+    }
+
+    #[test]
+    fn test_multi_line_block_comment() {
+        let lines = scan_lines("/* one\ntwo */ print").unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0][0].token,
+            Token::BlockComment(" one\ntwo ".to_string())
+        );
+        assert_eq!((lines[1][0].line, lines[1][0].column), (2, 8));
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        let err = scan_lines("print 1;\nprint \"open\n;").unwrap_err();
+        assert_eq!(
+            err,
+            ScanError {
+                line: 2,
+                column: 7,
+                message: "Unterminated string.".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_block_comment() {
+        let (remaining, tokens) = scan_tokens("1 /* a comment */ / 2");
+        assert_eq!(remaining, "");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Number(1.0),
+                Token::BlockComment(" a comment ".to_string()),
+                Token::Slash,
+                Token::Number(2.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nested_block_comment() {
+        let (remaining, comment) =
+            block_comment("/* outer /* inner */ still comment */ print").unwrap();
+        assert_eq!(remaining, " print");
+        assert_eq!(
+            comment,
+            Token::BlockComment(" outer /* inner */ still comment ".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        assert!(block_comment("/* outer /* inner */ never closed").is_err());
+
+        let err = scan_lines("print 1; /* never closed").unwrap_err();
+        assert_eq!(
+            err,
+            ScanError {
+                line: 1,
+                column: 10,
+                message: "Unterminated block comment.".to_string(),
+            }
+        );
+    }
+
     #[test]
     fn test_scan_line() {
         let input = "var x <= 10;";
@@ -272,7 +899,8 @@ mod tests {
     #[test]
     fn test_scan_line_2() {
         let input = "var and2 = 10;";
-        let (remaining, tokens) = scan_line(input).unwrap();
+        let (remaining, tokens) = scan_tokens(input);
+        assert_eq!(remaining, "");
 
         let expected_tokens = vec![
             Token::Var,
@@ -287,7 +915,8 @@ mod tests {
     #[test]
     fn test_scan_line_3() {
         let input = "andfunc for;  // This is a comment";
-        let (remaining, tokens) = scan_line(input).unwrap();
+        let (remaining, tokens) = scan_tokens(input);
+        assert_eq!(remaining, "");
 
         let expected_tokens = vec![
             Token::Identifier("andfunc".to_string()),
@@ -297,4 +926,249 @@ mod tests {
         ];
         assert_eq!(tokens, expected_tokens);
     }
+
+    #[test]
+    fn test_max_string_len() {
+        let options = ScannerOptions {
+            max_string_len: Some(5),
+        };
+
+        let (_, tokens) = scan_line_with_options("print \"short\";", &options).unwrap();
+        assert_eq!(tokens[1].token, Token::String("short".to_string()));
+
+        let err = scan_line_with_options("print \"too long\";", &options).unwrap_err();
+        assert_eq!(
+            err,
+            nom::Err::Failure(nom::error::Error::new(
+                "\"too long\";",
+                nom::error::ErrorKind::TooLarge
+            ))
+        );
+
+        // Unlimited by default
+        let long = format!("\"{}\"", "a".repeat(100_000));
+        let (remaining, tokens) = scan_tokens(&long);
+        assert_eq!(remaining, "");
+        assert_eq!(tokens, vec![Token::String("a".repeat(100_000))]);
+    }
+
+    #[test]
+    fn test_format_lines() {
+        let lines = scan_lines("var x = 1;\nprint x;").unwrap();
+        assert_eq!(
+            format_lines(&lines),
+            "line 1: Var Identifier(\"x\") Equal Number(1.0) Semicolon\n\
+             line 2: Print Identifier(\"x\") Semicolon Eof\n"
+        );
+    }
+
+    #[test]
+    fn test_tokenize_with_trivia_round_trip() {
+        let input = "var  x=\t1.50 ;// note\r\n\n   print x  ;\n";
+        let (remaining, tokens) = tokenize_with_trivia(input).unwrap();
+        assert_eq!(remaining, "");
+
+        let reconstructed: String = tokens.iter().map(|(_, lexeme)| *lexeme).collect();
+        assert_eq!(reconstructed, input);
+
+        assert_eq!(tokens[1], (Token::Whitespace("  ".to_string()), "  "));
+        assert_eq!(
+            tokens[8],
+            (Token::LineComment(" note".to_string()), "// note")
+        );
+        assert_eq!(tokens[9], (Token::Newline, "\r\n"));
+    }
+
+    #[test]
+    fn test_tokenize_with_trivia_shebang_and_carriage_return() {
+        let input = "#!/usr/bin/env rlox\nprint 1;\r print 2;";
+        assert!(scan_lines(input).is_ok());
+
+        let (remaining, tokens) = tokenize_with_trivia(input).unwrap();
+        assert_eq!(remaining, "");
+
+        let reconstructed: String = tokens.iter().map(|(_, lexeme)| *lexeme).collect();
+        assert_eq!(reconstructed, input);
+
+        assert_eq!(
+            tokens[0],
+            (
+                Token::Shebang("/usr/bin/env rlox".to_string()),
+                "#!/usr/bin/env rlox"
+            )
+        );
+        assert_eq!(tokens[1], (Token::Newline, "\n"));
+        assert_eq!(tokens[6], (Token::Whitespace("\r ".to_string()), "\r "));
+    }
+
+    #[test]
+    fn test_scan_lines_shebang() {
+        let input = "#!/usr/bin/env rlox\nvar x = 1;";
+        let lines = scan_lines(input).unwrap();
+
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].is_empty());
+        assert_eq!(lines[1][0].line, 2);
+        let tokens: Vec<Token> = lines
+            .into_iter()
+            .nth(1)
+            .unwrap()
+            .into_iter()
+            .map(|located| located.token)
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Var,
+                Token::Identifier("x".to_string()),
+                Token::Equal,
+                Token::Number(1.0),
+                Token::Semicolon,
+                Token::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_lines_shebang_error_line() {
+        let input = "#!/usr/bin/env rlox\nvar x = 1;\nvar y = @;";
+        let err = scan_lines(input).unwrap_err();
+        assert_eq!(err.line, 3);
+        assert_eq!(err.column, 9);
+    }
+
+    #[test]
+    fn test_empty_source_is_eof() {
+        let lines = scan_lines("").unwrap();
+        assert_eq!(
+            lines,
+            vec![vec![Located {
+                token: Token::Eof,
+                line: 1,
+                column: 1,
+                end_line: 1,
+                end_column: 1,
+            }]]
+        );
+    }
+
+    #[test]
+    fn test_whitespace_only_source_is_eof() {
+        let eof = |line, column| Located {
+            token: Token::Eof,
+            line,
+            column,
+            end_line: line,
+            end_column: column,
+        };
+        assert_eq!(scan_lines("\n").unwrap(), vec![vec![], vec![eof(2, 1)]]);
+        assert_eq!(scan_lines("\r\n").unwrap(), vec![vec![], vec![eof(2, 1)]]);
+        assert_eq!(scan_lines("   ").unwrap(), vec![vec![eof(1, 4)]]);
+    }
+
+    #[test]
+    fn test_eof_position() {
+        let lines = scan_lines("print 1;\n").unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[1],
+            vec![Located {
+                token: Token::Eof,
+                line: 2,
+                column: 1,
+                end_line: 2,
+                end_column: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_span_merge() {
+        let lines = scan_lines("print \"a\nb\";").unwrap();
+        let print = lines[0][0].span();
+        let string = lines[0][1].span();
+        assert_eq!(
+            print,
+            Span {
+                start: (1, 1),
+                end: (1, 6)
+            }
+        );
+        assert_eq!(
+            string,
+            Span {
+                start: (1, 7),
+                end: (2, 3)
+            }
+        );
+
+        let merged = Span {
+            start: (1, 1),
+            end: (2, 3),
+        };
+        assert_eq!(print.merge(&string), merged);
+        assert_eq!(string.merge(&print), merged);
+    }
+
+    #[test]
+    fn test_token_positions() {
+        let lines = scan_lines("var x = 1;\n  print x;").unwrap();
+        let positions: Vec<(u32, u32)> = lines
+            .iter()
+            .flatten()
+            .map(|located| (located.line, located.column))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                (1, 1),
+                (1, 5),
+                (1, 7),
+                (1, 9),
+                (1, 10),
+                (2, 3),
+                (2, 9),
+                (2, 10),
+                (2, 11)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stray_character_position() {
+        let err = scan_lines("var x = 1;\nprint \"é\" @ x;").unwrap_err();
+        assert_eq!(
+            err,
+            ScanError {
+                line: 2,
+                column: 11,
+                message: "Unexpected character.".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_scan_lines_string_too_long() {
+        let options = ScannerOptions {
+            max_string_len: Some(3),
+        };
+        let err = scan_lines_with_options("print \"abcd\";", &options).unwrap_err();
+        assert_eq!(
+            err,
+            ScanError {
+                line: 1,
+                column: 7,
+                message: "String literal too long.".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_scan_lines_hash_after_first_line() {
+        let input = "var x = 1;\n#!/usr/bin/env rlox";
+        assert!(scan_lines(input).is_err());
+
+        let input = "# not a shebang";
+        assert!(scan_lines(input).is_err());
+    }
 }
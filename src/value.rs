@@ -0,0 +1,82 @@
+use crate::format::{PrintOptions, format_number, format_number_with_options};
+use std::fmt;
+
+/// The result of evaluating an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Str(String),
+    Bool(bool),
+    Nil,
+}
+
+impl Value {
+    /// Lox truthiness: `nil` and `false` are falsey, everything else is truthy.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Bool(false))
+    }
+
+    /// The `Display` form, with numbers printed using the separators in `options`.
+    pub fn to_string_with(&self, options: &PrintOptions) -> String {
+        match self {
+            Value::Number(number) => format_number_with_options(*number, options),
+            _ => self.to_string(),
+        }
+    }
+}
+
+/// How `print` shows a value: whole numbers drop the `.0` and strings print without quotes.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Number(number) => write!(f, "{}", format_number(*number)),
+            Value::Str(string) => write!(f, "{}", string),
+            Value::Bool(boolean) => write!(f, "{}", boolean),
+            Value::Nil => write!(f, "nil"),
+        }
+    }
+}
+
+/// An error raised while evaluating, such as applying an operator to the wrong type.
+#[derive(Debug, PartialEq)]
+pub struct RuntimeError {
+    pub message: String,
+}
+
+impl RuntimeError {
+    pub fn new(message: &str) -> Self {
+        RuntimeError {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Runtime error: {}", self.message)
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Value::Number(10.0).to_string(), "10");
+        assert_eq!(Value::Number(1.5).to_string(), "1.5");
+        assert_eq!(Value::Number(-0.25).to_string(), "-0.25");
+        assert_eq!(Value::Str("hi".to_string()).to_string(), "hi");
+        assert_eq!(Value::Bool(false).to_string(), "false");
+        assert_eq!(Value::Nil.to_string(), "nil");
+    }
+
+    #[test]
+    fn test_to_string_with_options() {
+        let options = PrintOptions::new(',', Some('.')).unwrap();
+        assert_eq!(Value::Number(1234.5).to_string_with(&options), "1.234,5");
+        assert_eq!(Value::Bool(true).to_string_with(&options), "true");
+    }
+}
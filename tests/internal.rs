@@ -0,0 +1,11 @@
+#![cfg(feature = "testing")]
+
+use lox::tokens::Token;
+use lox::tokens::internal::number;
+
+#[test]
+fn test_number_parser() {
+    let (remaining, token) = number("42").unwrap();
+    assert_eq!(remaining, "");
+    assert_eq!(token, Token::Number(42.0));
+}
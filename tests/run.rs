@@ -0,0 +1,115 @@
+use lox::environment::Environment;
+use lox::format::PrintOptions;
+use lox::value::{RuntimeError, Value};
+use lox::{LoxError, run, run_line, run_with};
+
+/// Run `source` in a fresh environment and return what it printed.
+fn output(source: &str) -> String {
+    let mut out = Vec::new();
+    run_with(
+        source,
+        &mut Environment::new(),
+        &mut out,
+        &PrintOptions::default(),
+    )
+    .unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_run_print() {
+    assert_eq!(run("print 1 + 2;"), Ok(vec![Value::Number(3.0)]));
+}
+
+#[test]
+fn test_run_reports_stage() {
+    assert!(matches!(run("print \"open;"), Err(LoxError::Scan(_))));
+    assert!(matches!(run("print 1"), Err(LoxError::Parse(_))));
+    assert_eq!(
+        run("print -nil;"),
+        Err(LoxError::Runtime(RuntimeError::new(
+            "Operand must be a number."
+        )))
+    );
+}
+
+#[test]
+fn test_run_line_shares_environment() {
+    let mut env = Environment::new();
+    let mut out = Vec::new();
+
+    run_line("var x = 1;", &mut env, &mut out, &PrintOptions::default()).unwrap();
+    run_line("print x + 1;", &mut env, &mut out, &PrintOptions::default()).unwrap();
+    run_line("x * 10", &mut env, &mut out, &PrintOptions::default()).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "2\n10\n");
+
+    // Neither a statement nor an expression, so the statement error wins
+    assert!(matches!(
+        run_line("print", &mut env, &mut Vec::new(), &PrintOptions::default()),
+        Err(LoxError::Parse(_))
+    ));
+}
+
+#[test]
+// Dividing by zero is an error in integer mode
+#[cfg(not(feature = "integer-mode"))]
+fn test_print_non_finite_numbers() {
+    assert_eq!(output("print 1/0;"), "Infinity\n");
+    assert_eq!(output("print -1/0;"), "-Infinity\n");
+    assert_eq!(output("print 0/0;"), "NaN\n");
+
+    // The REPL echoes a bare expression the same way
+    let mut out = Vec::new();
+    run_line(
+        "-1/0",
+        &mut Environment::new(),
+        &mut out,
+        &PrintOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "-Infinity\n");
+}
+
+#[test]
+fn test_run_with_print_options() {
+    let options = PrintOptions::new(',', Some('.')).unwrap();
+    let mut out = Vec::new();
+    run_with("print 1234.5;", &mut Environment::new(), &mut out, &options).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "1.234,5\n");
+}
+
+#[test]
+fn test_print_comparisons() {
+    assert_eq!(output("print 1 < 2;"), "true\n");
+    assert_eq!(output("print 2 <= 1;"), "false\n");
+    assert_eq!(output("print !(1 == 1);"), "false\n");
+}
+
+#[test]
+fn test_errors_display_and_box() {
+    let err = run("print 1").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "[line 1] Error at column 8: Expect ';' after value."
+    );
+    assert_eq!(
+        run("print -nil;").unwrap_err().to_string(),
+        "Runtime error: Operand must be a number."
+    );
+
+    // Embedders can propagate with `?` into a boxed error
+    fn embedded() -> Result<Vec<Value>, Box<dyn std::error::Error>> {
+        Ok(run("print @;")?)
+    }
+    assert_eq!(
+        embedded().unwrap_err().to_string(),
+        "[line 1] Error at column 7: Unexpected character."
+    );
+}
+
+#[test]
+fn test_run_with_shebang() {
+    assert_eq!(output("#!/usr/bin/env rlox\nprint 1 + 2;\n"), "3\n");
+    assert_eq!(output("#!/usr/bin/env rlox\n"), "");
+    assert_eq!(output("#!/usr/bin/env rlox"), "");
+}